@@ -7,13 +7,17 @@ use ahash::AHashSet;
 use wasm_bindgen::prelude::*;
 
 use systems::{debug::{DebugEndLayer, DebugStartLayer, DrawShapeLayer}, input::{BaseDeviceType, DeviceEvent, DeviceTypeDescription, DeviceTypeDescriptionBuilder, InputReadLayer, InputSystem}, render::{IRenderDependencies, RenderLayers, RenderState}, ui::UITestLayer};
-use simple_layers::{layer::LayersStack, ILayersSystemDependencies};
+use simple_layers::{config::{LayersRegistry, LayersStackConfig}, layer::LayersStack, ILayersSystemDependencies};
+#[cfg(not(target_arch="wasm32"))]
+use simple_layers::plugin::PluginLoader;
 use tracing::Level;
 use tracing_subscriber::FmtSubscriber;
 use window::{device::DeviceCache, WindowCollection};
 use winit::{application::ApplicationHandler, event::{ElementState, MouseScrollDelta, WindowEvent}, event_loop::{ActiveEventLoop, ControlFlow, EventLoop}, keyboard::PhysicalKey, window::WindowAttributes};
 use xdi::{builder::DiBuilder, types::error::ServiceBuildResult, ServiceProvider};
 
+const DEFAULT_LAYERS_CONFIG: &str = include_str!("../layers.toml");
+
 pub mod systems;
 pub mod window;
 pub mod ui;
@@ -36,6 +40,7 @@ pub fn run() -> Result<(), impl std::error::Error> {
     }
 
     let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_time()
         .build()
         .expect("Tokio rt build error");
 
@@ -53,10 +58,17 @@ pub fn run() -> Result<(), impl std::error::Error> {
     di_builder.thread_local(WindowCollection::new);
     di_builder.transient(SimpleEngineApp::new);
 
+    // Plugins services should be registered before provider built
+    #[cfg(not(target_arch="wasm32"))]
+    let plugins = load_plugins(&di_builder);
+
     let sp = di_builder.build();
     
     let mut app = sp.resolve::<SimpleEngineApp>().expect("Engine app not registered");
 
+    #[cfg(not(target_arch="wasm32"))]
+    app.attach_plugins(plugins);
+
     let event_loop = EventLoop::new().expect("Event loop creation error");
 
     cfg_if::cfg_if! {
@@ -72,6 +84,40 @@ pub fn run() -> Result<(), impl std::error::Error> {
     res
 }
 
+/// Load plugins from SIMPLE_ENGINE_PLUGINS paths list
+#[cfg(not(target_arch="wasm32"))]
+fn load_plugins(di_builder: &DiBuilder) -> PluginLoader {
+    let mut plugins = PluginLoader::new();
+
+    if let Some(paths) = std::env::var_os("SIMPLE_ENGINE_PLUGINS") {
+        for path in std::env::split_paths(&paths) {
+            if let Err(err) = plugins.load(&path) {
+                tracing::error!("{err}");
+            }
+        }
+    }
+
+    plugins.register_services(di_builder);
+
+    plugins
+}
+
+/// Open layers stack inspector on SIMPLE_ENGINE_INSPECTOR endpoint, `host:port` or `unix:<path>`
+#[cfg(not(target_arch="wasm32"))]
+fn open_inspector(layers_stack: &mut LayersStack) {
+    let Ok(endpoint) = std::env::var("SIMPLE_ENGINE_INSPECTOR") else {
+        return;
+    };
+
+    let res = endpoint
+        .parse()
+        .and_then(|endpoint| layers_stack.open_inspector(endpoint).map(drop));
+
+    if let Err(err) = res {
+        tracing::error!("{err}");
+    }
+}
+
 #[derive(Debug)]
 pub struct SimpleEngineApp {
     window_collection: WindowCollection,
@@ -81,23 +127,43 @@ pub struct SimpleEngineApp {
     layers_stack: LayersStack,
 
     device_cache: DeviceCache,
+
+    /// Dropped after layers stack, plugin layers reference plugin code
+    #[cfg(not(target_arch="wasm32"))]
+    plugins: PluginLoader,
 }
 
 impl SimpleEngineApp {
     pub fn new(sp: ServiceProvider) -> ServiceBuildResult<Self> {
         let mut layers_stack = sp.resolve::<LayersStack>()?;
 
-        layers_stack.push_layer("debug_start", |_| Ok(DebugStartLayer::new()));
+        let mut registry = LayersRegistry::new();
 
-        layers_stack.push_layer("input_read", |sp| Ok(InputReadLayer::new(sp)?));
+        registry
+            .register("debug_start", |_| Ok(DebugStartLayer::new()))
+            .register("input_read", |sp| Ok(InputReadLayer::new(sp)?))
+            .register("debug_shape", |sp| Ok(DrawShapeLayer::new(sp)?))
+            .register("ui_test", |sp| Ok(UITestLayer::new(sp)?))
+            .register("debug_end", |sp| Ok(DebugEndLayer::new(sp)?));
 
-        layers_stack.push_layer("debug_shape", |sp| Ok(DrawShapeLayer::new(sp)?)).disable();
+        RenderLayers::register_constructors(&mut registry);
 
-        layers_stack.push_layer("ui_test", |sp| Ok(UITestLayer::new(sp)?));
+        let config = match std::env::var("SIMPLE_ENGINE_LAYERS") {
+            Ok(path) => LayersStackConfig::from_file(path),
+            Err(_) => LayersStackConfig::from_toml(DEFAULT_LAYERS_CONFIG),
+        }
+        .map_err(anyhow::Error::from)?;
 
-        layers_stack.register_source::<RenderLayers>();
-    
-        layers_stack.push_layer("debug_end", |sp| Ok(DebugEndLayer::new(sp)?));
+        let profile = std::env::var("SIMPLE_ENGINE_PROFILE").ok();
+
+        layers_stack
+            .apply_config(&registry, &config, profile.as_deref())
+            .map_err(anyhow::Error::from)?;
+
+        RenderLayers::apply_run_conditions(&mut layers_stack);
+
+        #[cfg(not(target_arch="wasm32"))]
+        open_inspector(&mut layers_stack);
 
         let input_system = sp.resolve::<InputSystem>()?;
 
@@ -110,8 +176,19 @@ impl SimpleEngineApp {
             input_system,
             device_cache: sp.resolve()?,
             layers_stack,
+            #[cfg(not(target_arch="wasm32"))]
+            plugins: PluginLoader::new(),
         })
     }
+
+    #[cfg(not(target_arch="wasm32"))]
+    pub fn attach_plugins(&mut self, mut plugins: PluginLoader) {
+        if let Err(err) = plugins.attach(&mut self.layers_stack) {
+            tracing::error!("{err}");
+        }
+
+        self.plugins = plugins;
+    }
 }
 
 impl ApplicationHandler for SimpleEngineApp {
@@ -186,6 +263,7 @@ impl ApplicationHandler for SimpleEngineApp {
     }
 
     fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
+        self.layers_stack.shutdown();
         self.window_collection.free();
     }
 
@@ -203,8 +281,18 @@ impl ApplicationHandler for SimpleEngineApp {
         }
     }
 
-    fn about_to_wait(&mut self, _event_loop: &ActiveEventLoop) {
-        self.layers_stack.update();
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        #[cfg(not(target_arch="wasm32"))]
+        if let Err(err) = self.plugins.poll_reload(&mut self.layers_stack) {
+            tracing::error!("{err}");
+        }
+
+        if let Err(err) = self.layers_stack.update() {
+            tracing::error!("Layers stack update aborted: {err}");
+            event_loop.exit();
+            return;
+        }
+
         self.window_collection.get_window().expect("Base window not registered").request_redraw();
     }
 }
\ No newline at end of file
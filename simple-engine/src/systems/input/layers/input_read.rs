@@ -18,7 +18,7 @@ impl InputReadLayer {
 }
 
 impl ILayer for InputReadLayer {
-    fn on_update(&mut self, _dt: &chrono::TimeDelta, scheduler: &mut simple_layers::scheduler::LayerScheduler) {
+    fn on_update(&mut self, _dt: &chrono::TimeDelta, scheduler: &mut simple_layers::scheduler::LayerScheduler) -> anyhow::Result<()> {
         let input_system = self.input_system.clone();
 
         scheduler.schedule(async move {
@@ -32,5 +32,7 @@ impl ILayer for InputReadLayer {
 
             tracing::info!("All events: {:?}", all_events);
         }, ());
+
+        Ok(())
     }
 }
\ No newline at end of file
@@ -28,8 +28,8 @@ impl RenderCommansLayer {
 }
 
 impl ILayer for RenderCommansLayer {
-    fn on_update(&mut self, _dt: &chrono::TimeDelta, scheduler: &mut simple_layers::scheduler::LayerScheduler) {
-        scheduler.wait_all_blocking();
+    fn on_update(&mut self, _dt: &chrono::TimeDelta, scheduler: &mut simple_layers::scheduler::LayerScheduler) -> anyhow::Result<()> {
+        scheduler.wait_all_blocking()?;
 
         self.render_pipeline_manager.enable_render_pipeline("default");
 
@@ -81,5 +81,7 @@ impl ILayer for RenderCommansLayer {
             };
 
         }
+
+        Ok(())
     }
 }
\ No newline at end of file
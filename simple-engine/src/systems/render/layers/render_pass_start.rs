@@ -1,4 +1,4 @@
-use simple_layers::layer::ILayer;
+use simple_layers::{layer::ILayer, types::task::TaskOptions};
 use xdi::{ServiceProvider, types::error::ServiceBuildResult};
 
 use crate::systems::render::{FrameOutputState, RenderState};
@@ -23,11 +23,11 @@ impl ILayer for RenderPassStartLayer {
         &mut self,
         _dt: &chrono::TimeDelta,
         scheduler: &mut simple_layers::scheduler::LayerScheduler,
-    ) {
+    ) -> anyhow::Result<()> {
         let render_state = self.render_state.clone();
         let output_state = self.output_state.clone();
 
-        scheduler.schedule(async move {
+        scheduler.schedule_with_options(async move {
             let render_state = render_state.get();
 
             let Some(render_state) = &*render_state else {
@@ -45,6 +45,8 @@ impl ILayer for RenderPassStartLayer {
     
                 *output_state = Some(output);
             }
-        }, ["render_pipeline_init"]);
+        }, ["render_pipeline_init"], TaskOptions::new().reads::<RenderState>().writes::<FrameOutputState>());
+
+        Ok(())
     }
 }
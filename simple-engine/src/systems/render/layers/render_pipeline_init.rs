@@ -1,7 +1,7 @@
-use simple_layers::layer::ILayer;
+use simple_layers::{layer::ILayer, types::task::TaskOptions};
 use xdi::{types::error::ServiceBuildResult, ServiceProvider};
 
-use crate::systems::render::RenderPipelineManager;
+use crate::systems::render::{RenderPipelineManager, ShaderManager};
 
 
 #[derive(Debug)]
@@ -18,13 +18,15 @@ impl RenderPipelineInitLayer {
 }
 
 impl ILayer for RenderPipelineInitLayer {
-    fn on_update(&mut self, _dt: &chrono::TimeDelta, scheduler: &mut simple_layers::scheduler::LayerScheduler) {
+    fn on_update(&mut self, _dt: &chrono::TimeDelta, scheduler: &mut simple_layers::scheduler::LayerScheduler) -> anyhow::Result<()> {
         let render_pipeline_manager = self.render_pipeline_manager.clone();
 
-        scheduler.schedule(async move {
+        scheduler.schedule_with_options(async move {
             if !render_pipeline_manager.has_pipeline("default") {
                 render_pipeline_manager.add_pipeline("default");
             }
-        }, ["shader_init_layer"]);
+        }, ["shader_init_layer"], TaskOptions::new().reads::<ShaderManager>().writes::<RenderPipelineManager>());
+
+        Ok(())
     }
 }
\ No newline at end of file
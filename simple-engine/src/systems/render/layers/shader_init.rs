@@ -1,4 +1,4 @@
-use simple_layers::layer::ILayer;
+use simple_layers::{layer::ILayer, types::task::TaskOptions};
 use xdi::{types::error::ServiceBuildResult, ServiceProvider};
 
 use crate::systems::render::ShaderManager;
@@ -19,13 +19,15 @@ impl ShaderInitLayer {
 }
 
 impl ILayer for ShaderInitLayer {
-    fn on_update(&mut self, _dt: &chrono::TimeDelta, scheduler: &mut simple_layers::scheduler::LayerScheduler) {
+    fn on_update(&mut self, _dt: &chrono::TimeDelta, scheduler: &mut simple_layers::scheduler::LayerScheduler) -> anyhow::Result<()> {
         let shader_collection = self.shader_collection.clone();
 
-        scheduler.schedule(async move {
+        scheduler.schedule_with_options(async move {
             if !shader_collection.has_shader("default") {
                 shader_collection.load_shader("default", "./simple-engine/shaders/shader.wgsl");
             }
-        }, ["render_state_init"]);
+        }, ["render_state_init"], TaskOptions::new().writes::<ShaderManager>());
+
+        Ok(())
     }
 }
\ No newline at end of file
@@ -20,7 +20,7 @@ pub mod render_pipeline;
 pub use render_pipeline::*;
 
 use xdi::builder::DiBuilder;
-use simple_layers::layer::ILayersSource;
+use simple_layers::{condition::RunCondition, config::LayersRegistry, layer::{ILayersSource, LayersStack}, stage::Stage};
 
 pub trait IRenderDependencies {
     fn register_render_dependencies(&self);
@@ -39,16 +39,41 @@ impl IRenderDependencies for DiBuilder {
 
 pub struct RenderLayers;
 
-impl ILayersSource for RenderLayers {
-    fn register(layers_stack: &mut simple_layers::layer::LayersStack) {
-        layers_stack.push_layer("render_state_init", |sp| Ok(RenderStateInitLayer::new(sp)?));
-        layers_stack.push_layer("shader_init_layer", |sp| Ok(ShaderInitLayer::new(sp)?));
-        layers_stack.push_layer("render_pipeline_init", |sp| Ok(RenderPipelineInitLayer::new(sp)?));
+/// Render layers in update order
+const RENDER_LAYERS: [&str; 6] = ["render_state_init", "shader_init_layer", "render_pipeline_init", "render_pass_start", "render_command", "render_pass_end"];
 
-        layers_stack.push_layer("render_pass_start", |sp| Ok(RenderPassStartLayer::new(sp)?));
+/// Render layers pushed in render stage, run conditions applied by `apply_run_conditions`
+impl ILayersSource for RenderLayers {
+    fn register(layers_stack: &mut LayersStack) {
+        layers_stack.push_group("render", |stack| {
+            stack.push_layer("render_state_init", |sp| Ok(RenderStateInitLayer::new(sp)?)).with_stage(Stage::Render);
+            stack.push_layer("shader_init_layer", |sp| Ok(ShaderInitLayer::new(sp)?)).with_stage(Stage::Render);
+            stack.push_layer("render_pipeline_init", |sp| Ok(RenderPipelineInitLayer::new(sp)?)).with_stage(Stage::Render);
+            stack.push_layer("render_pass_start", |sp| Ok(RenderPassStartLayer::new(sp)?)).with_stage(Stage::Render);
+            stack.push_layer("render_command", |sp| Ok(RenderCommansLayer::new(sp)?)).with_stage(Stage::Render);
+            stack.push_layer("render_pass_end", |sp| Ok(RenderPassEndLayer::new(sp)?)).with_stage(Stage::Render);
+        });
+    }
+}
 
-        layers_stack.push_layer("render_command", |sp| Ok(RenderCommansLayer::new(sp)?));
+impl RenderLayers {
+    /// Render layers constructors for config built stack
+    pub fn register_constructors(registry: &mut LayersRegistry) {
+        registry
+            .register("render_state_init", |sp| Ok(RenderStateInitLayer::new(sp)?))
+            .register("shader_init_layer", |sp| Ok(ShaderInitLayer::new(sp)?))
+            .register("render_pipeline_init", |sp| Ok(RenderPipelineInitLayer::new(sp)?))
+            .register("render_pass_start", |sp| Ok(RenderPassStartLayer::new(sp)?))
+            .register("render_command", |sp| Ok(RenderCommansLayer::new(sp)?))
+            .register("render_pass_end", |sp| Ok(RenderPassEndLayer::new(sp)?));
+    }
 
-        layers_stack.push_layer("render_pass_end", |sp| Ok(RenderPassEndLayer::new(sp)?));
+    /// Layers after render state init updated only while render state created
+    pub fn apply_run_conditions(layers_stack: &mut LayersStack) {
+        for name in &RENDER_LAYERS[1..] {
+            if let Some(layer) = layers_stack.get_layer_mut(*name) {
+                layer.with_run_condition(RunCondition::resource_exists::<RenderState>(|state| state.get().is_some()));
+            }
+        }
     }
-}
\ No newline at end of file
+}
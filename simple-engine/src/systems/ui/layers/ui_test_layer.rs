@@ -57,7 +57,7 @@ impl UITestLayer {
 }
 
 impl ILayer for UITestLayer {
-    fn on_update(&mut self, _dt: &chrono::TimeDelta, scheduler: &mut simple_layers::scheduler::LayerScheduler) {
+    fn on_update(&mut self, _dt: &chrono::TimeDelta, scheduler: &mut simple_layers::scheduler::LayerScheduler) -> anyhow::Result<()> {
         let render_state = self.render_state.clone();
         let material_system = self.material_system.clone();
         let render_commands_manager = self.render_commands_manager.clone();
@@ -86,6 +86,8 @@ impl ILayer for UITestLayer {
     
             render_commands_manager.add_buffer(render_command_buffer);
         }, ());
+
+        Ok(())
     }
 }
 
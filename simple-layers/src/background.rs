@@ -0,0 +1,305 @@
+use std::{collections::HashMap, pin::Pin, sync::Arc, time::Duration};
+
+use parking_lot::Mutex;
+use tokio::{runtime::Handle, task::JoinHandle};
+use xdi::IAsyncTaskScope;
+
+use crate::{
+    layer::LayerTaskCtx,
+    scheduler::run_task,
+    types::{id::LayerId, sync::CancellationToken, task::TaskOptions},
+};
+
+type BackgroundFuture = Pin<Box<dyn Future<Output = anyhow::Result<()>> + Send>>;
+
+type BackgroundFactory = Arc<dyn Fn(CancellationToken) -> BackgroundFuture + Send + Sync>;
+
+/// Reaction on background service error or panic
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RestartPolicy {
+    /// Service stay failed
+    #[default]
+    Never,
+    /// Restart service, service failed after `attempts` restarts
+    OnFailure { attempts: usize },
+    Always,
+}
+
+/// Background service settings.
+/// Restart backoff require tokio runtime with enabled time driver
+#[derive(Debug, Clone, Default)]
+pub struct BackgroundOptions {
+    pub(crate) restart: RestartPolicy,
+    pub(crate) backoff: Option<Duration>,
+}
+
+impl BackgroundOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_restart(mut self, restart: RestartPolicy) -> Self {
+        self.restart = restart;
+        self
+    }
+
+    /// Delay before service restart
+    pub fn with_backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = Some(backoff);
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BackgroundState {
+    Running,
+    /// Failed and waiting restart backoff
+    Restarting,
+    /// Stopped while owning layer disabled, started again on enable
+    Stopped,
+    Completed,
+    /// Failed without restart
+    Failed,
+}
+
+/// Background service state for stack inspection
+#[derive(Debug, Clone)]
+pub struct BackgroundServiceInfo {
+    pub layer_id: LayerId,
+    pub layer_name: String,
+    pub name: String,
+    pub state: BackgroundState,
+    pub restarts: usize,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug)]
+struct BackgroundStatus {
+    state: BackgroundState,
+    restarts: usize,
+    last_error: Option<String>,
+}
+
+struct BackgroundService {
+    name: String,
+    /// Spawning layer context, propagated into service task
+    layer: LayerTaskCtx,
+    factory: BackgroundFactory,
+    options: BackgroundOptions,
+    status: Arc<Mutex<BackgroundStatus>>,
+    cancellation: CancellationToken,
+    /// Supervisor of last start
+    supervisor: Option<JoinHandle<()>>,
+}
+
+impl std::fmt::Debug for BackgroundService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BackgroundService")
+            .field("name", &self.name)
+            .field("layer", &self.layer)
+            .field("status", &self.status)
+            .finish()
+    }
+}
+
+impl BackgroundService {
+    /// Run supervisor, service restarted by policy until cancelled
+    fn start(&mut self, handle: &Handle) {
+        self.cancellation = CancellationToken::new();
+
+        let factory = self.factory.clone();
+        let options = self.options.clone();
+        let status = self.status.clone();
+        let cancellation = self.cancellation.clone();
+        let (name, layer_name) = (self.name.clone(), self.layer.name.clone());
+
+        status.lock().state = BackgroundState::Running;
+
+        let supervisor = async move {
+            let mut attempts = 0;
+
+            loop {
+                let res = run_task(factory(cancellation.clone()), cancellation.clone(), TaskOptions::default()).await;
+
+                // State already changed by stop
+                if cancellation.is_cancelled() {
+                    return;
+                }
+
+                let message = match res {
+                    Ok(Ok(())) => {
+                        status.lock().state = BackgroundState::Completed;
+                        return;
+                    }
+                    Ok(Err(err)) => format!("{err:?}"),
+                    Err((_, message)) => message,
+                };
+
+                tracing::error!("[{layer_name}] Background service [{name}] failed: {message}");
+
+                attempts += 1;
+
+                let restart = match options.restart {
+                    RestartPolicy::Never => false,
+                    RestartPolicy::OnFailure { attempts: max_attempts } => attempts <= max_attempts,
+                    RestartPolicy::Always => true,
+                };
+
+                {
+                    let mut status = status.lock();
+
+                    status.last_error = Some(message);
+                    status.state = if restart {
+                        BackgroundState::Restarting
+                    } else {
+                        BackgroundState::Failed
+                    };
+                }
+
+                if !restart {
+                    return;
+                }
+
+                if let Some(backoff) = options.backoff
+                    && run_task(tokio::time::sleep(backoff), cancellation.clone(), TaskOptions::default())
+                        .await
+                        .is_err()
+                {
+                    return;
+                }
+
+                if cancellation.is_cancelled() {
+                    return;
+                }
+
+                tracing::debug!("[{layer_name}] Background service [{name}] restarted");
+
+                let mut status = status.lock();
+
+                status.state = BackgroundState::Running;
+                status.restarts += 1;
+            }
+        };
+
+        self.supervisor = Some(handle.spawn(self.layer.clone().scope(supervisor).add_service_span()));
+    }
+
+    fn stop(&mut self) {
+        self.cancellation.cancel();
+
+        let mut status = self.status.lock();
+
+        if matches!(status.state, BackgroundState::Running | BackgroundState::Restarting) {
+            status.state = BackgroundState::Stopped;
+        }
+    }
+}
+
+/// Layers background services, live across frames until owning layer detached
+#[derive(Debug, Default)]
+pub(crate) struct BackgroundServices {
+    services: HashMap<LayerId, Vec<BackgroundService>, ahash::RandomState>,
+    /// Supervisors of removed services, still running until cancellation observed
+    removed: Vec<JoinHandle<()>>,
+}
+
+impl BackgroundServices {
+    /// Start service, service with same name of layer replaced
+    pub(crate) fn spawn<TFut: Future<Output = anyhow::Result<()>> + Send + 'static>(
+        &mut self,
+        handle: &Handle,
+        layer: LayerTaskCtx,
+        name: String,
+        factory: impl Fn(CancellationToken) -> TFut + Send + Sync + 'static,
+        options: BackgroundOptions,
+    ) {
+        let services = self.services.entry(layer.id).or_default();
+
+        if let Some(index) = services.iter().position(|service| service.name == name) {
+            let mut service = services.remove(index);
+
+            service.stop();
+            self.removed.extend(service.supervisor);
+        }
+
+        let mut service = BackgroundService {
+            name,
+            layer,
+            factory: Arc::new(move |cancellation| Box::pin(factory(cancellation))),
+            options,
+            status: Arc::new(Mutex::new(BackgroundStatus {
+                state: BackgroundState::Running,
+                restarts: 0,
+                last_error: None,
+            })),
+            cancellation: CancellationToken::new(),
+            supervisor: None,
+        };
+
+        service.start(handle);
+
+        services.push(service);
+    }
+
+    /// Stop layer services while layer disabled
+    pub(crate) fn stop_of(&mut self, layer_id: LayerId) {
+        for service in self.services.get_mut(&layer_id).into_iter().flatten() {
+            service.stop();
+        }
+    }
+
+    /// Start again services stopped by `stop_of`
+    pub(crate) fn resume_of(&mut self, handle: &Handle, layer_id: LayerId) {
+        for service in self.services.get_mut(&layer_id).into_iter().flatten() {
+            if service.status.lock().state == BackgroundState::Stopped {
+                service.start(handle);
+            }
+        }
+    }
+
+    /// Cancel layer services, supervisors kept until waited by `wait_removed_blocking`
+    pub(crate) fn remove_of(&mut self, layer_id: LayerId) {
+        self.removed.retain(|supervisor| !supervisor.is_finished());
+
+        for mut service in self.services.remove(&layer_id).into_iter().flatten() {
+            service.stop();
+            self.removed.extend(service.supervisor);
+        }
+    }
+
+    /// Wait supervisors of removed services, so code of services may be unloaded
+    pub(crate) fn wait_removed_blocking(&mut self, handle: &Handle) {
+        let removed = std::mem::take(&mut self.removed);
+
+        handle.block_on(async {
+            for supervisor in removed {
+                _ = supervisor.await;
+            }
+        });
+    }
+
+    pub(crate) fn infos(&self) -> Vec<BackgroundServiceInfo> {
+        let mut infos = self
+            .services
+            .iter()
+            .flat_map(|(layer_id, services)| {
+                services.iter().map(|service| {
+                    let status = service.status.lock();
+
+                    BackgroundServiceInfo {
+                        layer_id: *layer_id,
+                        layer_name: service.layer.name.clone(),
+                        name: service.name.clone(),
+                        state: status.state.clone(),
+                        restarts: status.restarts,
+                        last_error: status.last_error.clone(),
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        infos.sort_by(|a, b| (&a.layer_name, &a.name).cmp(&(&b.layer_name, &b.name)));
+
+        infos
+    }
+}
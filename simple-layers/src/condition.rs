@@ -0,0 +1,98 @@
+use chrono::TimeDelta;
+use xdi::ServiceProvider;
+
+type RunPredicate = Box<dyn FnMut(&ServiceProvider) -> bool>;
+
+/// Layer update condition, checked every frame while layer active.
+/// Layer not built and not updated while any condition false
+pub struct RunCondition(RunConditionKind);
+
+enum RunConditionKind {
+    If(RunPredicate),
+    EveryNthFrame { n: u32, frame: u32 },
+    /// Elapsed time since last run, `None` before first run
+    Interval { interval: TimeDelta, elapsed: Option<TimeDelta> },
+}
+
+impl std::fmt::Debug for RunCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.0 {
+            RunConditionKind::If(_) => f.debug_tuple("If").finish(),
+            RunConditionKind::EveryNthFrame { n, frame } => {
+                f.debug_struct("EveryNthFrame").field("n", n).field("frame", frame).finish()
+            }
+            RunConditionKind::Interval { interval, elapsed } => f
+                .debug_struct("Interval")
+                .field("interval", interval)
+                .field("elapsed", elapsed)
+                .finish(),
+        }
+    }
+}
+
+impl RunCondition {
+    /// Run while predicate returns true
+    pub fn run_if(predicate: impl FnMut(&ServiceProvider) -> bool + 'static) -> Self {
+        Self(RunConditionKind::If(Box::new(predicate)))
+    }
+
+    /// Run while resource resolved and predicate over it returns true, for example while state initialized
+    pub fn resource_exists<TResource: 'static>(predicate: impl Fn(&TResource) -> bool + 'static) -> Self {
+        Self::run_if(move |sp| sp.resolve::<TResource>().is_ok_and(|resource| predicate(&resource)))
+    }
+
+    /// Run on first checked frame and every Nth frame after it
+    pub fn every_nth_frame(n: u32) -> Self {
+        assert!(n > 0, "Frames interval should be positive");
+
+        Self(RunConditionKind::EveryNthFrame { n, frame: 0 })
+    }
+
+    /// Run at most once per interval of frames time, first checked frame runs
+    pub fn interval(interval: TimeDelta) -> Self {
+        assert!(interval > TimeDelta::zero(), "Run interval should be positive");
+
+        Self(RunConditionKind::Interval { interval, elapsed: None })
+    }
+
+    /// Run at most `hz` times per second of frames time, panics if `hz` is zero
+    pub fn max_rate(hz: u32) -> Self {
+        assert!(hz > 0, "Run rate should be positive");
+
+        Self::interval(TimeDelta::nanoseconds(1_000_000_000 / hz as i64))
+    }
+
+    pub(crate) fn check(&mut self, sp: &ServiceProvider, dt: &TimeDelta) -> bool {
+        match &mut self.0 {
+            RunConditionKind::If(predicate) => predicate(sp),
+            RunConditionKind::EveryNthFrame { n, frame } => {
+                let run = *frame == 0;
+
+                *frame = (*frame + 1) % *n;
+
+                run
+            }
+            RunConditionKind::Interval { interval, elapsed } => {
+                let Some(elapsed) = elapsed else {
+                    *elapsed = Some(TimeDelta::zero());
+                    return true;
+                };
+
+                *elapsed += *dt;
+
+                if *elapsed < *interval {
+                    return false;
+                }
+
+                *elapsed -= *interval;
+
+                // Long frame runs layer once, without catching up
+                if *elapsed >= *interval {
+                    *elapsed = TimeDelta::zero();
+                }
+
+                true
+            }
+        }
+    }
+}
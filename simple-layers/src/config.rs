@@ -0,0 +1,299 @@
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use serde::{Deserialize, de::DeserializeOwned};
+use xdi::ServiceProvider;
+
+use crate::{
+    layer::{ILayer, LayersStack},
+    stage::Stage,
+    types::error::LayersConfigError,
+};
+
+/// Layers stack description, layers pushed in declaration order and sorted by stages on update
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LayersStackConfig {
+    #[serde(default)]
+    pub layers: Vec<LayerConfig>,
+    #[serde(default)]
+    pub profiles: HashMap<String, LayersProfileConfig>,
+    /// Frames pipelining depth, see `LayersStack::set_pipeline_depth`
+    #[serde(default)]
+    pub pipeline_depth: Option<usize>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LayerConfig {
+    pub name: String,
+    /// Registry constructor name, layer name used if not set
+    #[serde(default)]
+    pub layer: Option<String>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub group: Option<String>,
+    #[serde(default)]
+    pub stage: Stage,
+    /// Layers names in same stage updated after this layer
+    #[serde(default)]
+    pub before: Vec<String>,
+    /// Layers names in same stage updated before this layer
+    #[serde(default)]
+    pub after: Vec<String>,
+    #[serde(default)]
+    pub params: serde_json::Map<String, serde_json::Value>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Profile overrides, targets are layer or group names
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LayersProfileConfig {
+    #[serde(default)]
+    pub enable: Vec<String>,
+    #[serde(default)]
+    pub disable: Vec<String>,
+    /// Layers not pushed to stack at all
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+impl LayersStackConfig {
+    pub fn from_json(json: &str) -> Result<Self, LayersConfigError> {
+        serde_json::from_str(json).map_err(|err| LayersConfigError::Parse { message: err.to_string() })
+    }
+
+    pub fn from_toml(toml: &str) -> Result<Self, LayersConfigError> {
+        toml::from_str(toml).map_err(|err| LayersConfigError::Parse { message: err.to_string() })
+    }
+
+    /// Load `.json` or `.toml` file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, LayersConfigError> {
+        let path = path.as_ref();
+
+        let content = std::fs::read_to_string(path).map_err(|err| LayersConfigError::Io {
+            path: path.display().to_string(),
+            message: err.to_string(),
+        })?;
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Self::from_json(&content),
+            Some("toml") => Self::from_toml(&content),
+            _ => Err(LayersConfigError::UnsupportedFormat {
+                path: path.display().to_string(),
+            }),
+        }
+    }
+
+    /// Layers with applied profile overrides
+    pub fn resolve(&self, profile: Option<&str>) -> Result<Vec<LayerConfig>, LayersConfigError> {
+        let mut layers = self.layers.clone();
+
+        let Some(profile_name) = profile else {
+            return Ok(layers);
+        };
+
+        let Some(profile) = self.profiles.get(profile_name) else {
+            return Err(LayersConfigError::UnknownProfile {
+                profile: profile_name.to_string(),
+            });
+        };
+
+        let is_target = |layer: &LayerConfig, target: &str| layer.name == target || layer.group.as_deref() == Some(target);
+
+        for target in profile.enable.iter().chain(&profile.disable).chain(&profile.exclude) {
+            if !layers.iter().any(|layer| is_target(layer, target)) {
+                return Err(LayersConfigError::UnknownProfileTarget {
+                    profile: profile_name.to_string(),
+                    target: target.clone(),
+                });
+            }
+        }
+
+        for layer in &mut layers {
+            if profile.enable.iter().any(|target| is_target(layer, target)) {
+                layer.enabled = true;
+            }
+
+            if profile.disable.iter().any(|target| is_target(layer, target)) {
+                layer.enabled = false;
+            }
+        }
+
+        layers.retain(|layer| !profile.exclude.iter().any(|target| is_target(layer, target)));
+
+        Ok(layers)
+    }
+}
+
+/// Push prepared layer with config name
+type PushLayer = Box<dyn FnOnce(&mut LayersStack, String)>;
+
+/// Prepare layer from config parameters
+type LayerFactory = Box<dyn Fn(&serde_json::Value) -> Result<PushLayer, String>>;
+
+/// Named layers constructors for `LayersStack::apply_config`
+#[derive(Default)]
+pub struct LayersRegistry {
+    factories: HashMap<String, LayerFactory, ahash::RandomState>,
+}
+
+impl std::fmt::Debug for LayersRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LayersRegistry")
+            .field("layers", &self.factories.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl LayersRegistry {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn register<
+        TLayer: ILayer + 'static,
+        TCtr: Fn(ServiceProvider) -> anyhow::Result<TLayer> + 'static,
+    >(
+        &mut self,
+        name: impl Into<String>,
+        layer_ctr: TCtr,
+    ) -> &mut Self {
+        let layer_ctr = Arc::new(layer_ctr);
+
+        self.factories.insert(
+            name.into(),
+            Box::new(move |_| {
+                let layer_ctr = layer_ctr.clone();
+
+                Ok(Box::new(move |stack, name| {
+                    stack.push_layer(name, move |sp| layer_ctr(sp));
+                }))
+            }),
+        );
+
+        self
+    }
+
+    /// Register constructor with typed parameters, parameters deserialized when stack built
+    pub fn register_with_params<
+        TParams: DeserializeOwned + 'static,
+        TLayer: ILayer + 'static,
+        TCtr: Fn(ServiceProvider, &TParams) -> anyhow::Result<TLayer> + 'static,
+    >(
+        &mut self,
+        name: impl Into<String>,
+        layer_ctr: TCtr,
+    ) -> &mut Self {
+        let layer_ctr = Arc::new(layer_ctr);
+
+        self.factories.insert(
+            name.into(),
+            Box::new(move |params| {
+                let params = TParams::deserialize(params).map_err(|err| err.to_string())?;
+                let layer_ctr = layer_ctr.clone();
+
+                Ok(Box::new(move |stack, name| {
+                    stack.push_layer(name, move |sp| layer_ctr(sp, &params));
+                }))
+            }),
+        );
+
+        self
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.factories.contains_key(name)
+    }
+}
+
+impl LayersStack {
+    /// Validate config against registry and push layers, stack not changed on validation error
+    pub fn apply_config(
+        &mut self,
+        registry: &LayersRegistry,
+        config: &LayersStackConfig,
+        profile: Option<&str>,
+    ) -> Result<(), LayersConfigError> {
+        let layers = config.resolve(profile)?;
+
+        for (index, layer) in layers.iter().enumerate() {
+            let ty = layer.layer.as_deref().unwrap_or(&layer.name);
+
+            if !registry.contains(ty) {
+                return Err(LayersConfigError::UnknownLayer {
+                    layer: layer.name.clone(),
+                    ty: ty.to_string(),
+                });
+            }
+
+            if layers[..index].iter().any(|prev| prev.name == layer.name) || self.get_layer(&layer.name).is_some() {
+                return Err(LayersConfigError::DuplicateLayerName {
+                    name: layer.name.clone(),
+                });
+            }
+        }
+
+        // Order targets are config layers or layers already in stack
+        for layer in &layers {
+            for target in layer.before.iter().chain(&layer.after) {
+                if !layers.iter().any(|other| &other.name == target) && self.get_layer(target).is_none() {
+                    return Err(LayersConfigError::UnknownOrderTarget {
+                        layer: layer.name.clone(),
+                        target: target.clone(),
+                    });
+                }
+            }
+        }
+
+        // Parameters deserialized before any layer pushed
+        let mut prepared = Vec::with_capacity(layers.len());
+
+        for layer in &layers {
+            let ty = layer.layer.as_deref().unwrap_or(&layer.name);
+            let params = serde_json::Value::Object(layer.params.clone());
+
+            let push = (registry.factories[ty])(&params).map_err(|message| LayersConfigError::InvalidParams {
+                layer: layer.name.clone(),
+                message,
+            })?;
+
+            prepared.push((layer, push));
+        }
+
+        for (layer, push) in prepared {
+            match &layer.group {
+                Some(group) => self.push_group(group, |stack| push(stack, layer.name.clone())),
+                None => push(self, layer.name.clone()),
+            }
+
+            let Some(pushed) = self.get_layer_mut(&layer.name) else {
+                continue;
+            };
+
+            pushed.with_stage(layer.stage);
+
+            for target in &layer.before {
+                pushed.before(target.clone());
+            }
+
+            for target in &layer.after {
+                pushed.after(target.clone());
+            }
+
+            if !layer.enabled {
+                pushed.disable();
+            }
+        }
+
+        if let Some(depth) = config.pipeline_depth {
+            self.set_pipeline_depth(depth);
+        }
+
+        Ok(())
+    }
+}
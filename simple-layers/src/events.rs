@@ -0,0 +1,301 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use parking_lot::Mutex;
+use xdi::{ServiceProvider, builder::DiBuilder, types::error::ServiceBuildResult};
+
+use crate::{layer::LayerCtx, scheduler::Dependency};
+
+pub trait ILayersEventsDependencies {
+    /// Register `Events<T>` singleton
+    fn register_events<T: Send + Sync + 'static>(&self);
+}
+
+impl ILayersEventsDependencies for DiBuilder {
+    fn register_events<T: Send + Sync + 'static>(&self) {
+        self.singletone(Events::<T>::new);
+    }
+}
+
+/// Typed events channel, events written in frame N readable in frames N and N + 1
+pub struct Events<T> {
+    buffer: Arc<Mutex<EventsBuffer<T>>>,
+    /// Writers count by layer name
+    writers: Arc<Mutex<HashMap<String, usize, ahash::RandomState>>>,
+    sp: ServiceProvider,
+}
+
+impl<T> Clone for Events<T> {
+    fn clone(&self) -> Self {
+        Self {
+            buffer: self.buffer.clone(),
+            writers: self.writers.clone(),
+            sp: self.sp.clone(),
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for Events<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Events")
+            .field("type", &std::any::type_name::<T>())
+            .field("writers", &self.writers())
+            .finish()
+    }
+}
+
+struct EventsBuffer<T> {
+    previous: Vec<T>,
+    current: Vec<T>,
+    /// Id of first previous frame event
+    previous_start: u64,
+}
+
+impl<T> EventsBuffer<T> {
+    fn current_start(&self) -> u64 {
+        self.previous_start + self.previous.len() as u64
+    }
+
+    fn end(&self) -> u64 {
+        self.current_start() + self.current.len() as u64
+    }
+}
+
+impl<T: Send + Sync + 'static> Events<T> {
+    pub fn new(sp: ServiceProvider) -> ServiceBuildResult<Self> {
+        let events = Self {
+            buffer: Arc::new(Mutex::new(EventsBuffer {
+                previous: Vec::new(),
+                current: Vec::new(),
+                previous_start: 0,
+            })),
+            writers: Default::default(),
+            sp: sp.clone(),
+        };
+
+        let buffer = events.buffer.clone();
+
+        sp.resolve::<EventsRegistry>()?.add(Box::new(move || {
+            let mut buffer = buffer.lock();
+
+            buffer.previous_start = buffer.current_start();
+            buffer.previous = std::mem::take(&mut buffer.current);
+        }));
+
+        Ok(events)
+    }
+}
+
+impl<T> Events<T> {
+    /// Writer tagged by current layer, scheduler dependency on events resolved to writers layers
+    pub fn writer(&self) -> EventWriter<T> {
+        let layer = self.sp.resolve::<LayerCtx>().unwrap().name();
+
+        *self.writers.lock().entry(layer.clone()).or_default() += 1;
+
+        EventWriter {
+            events: self.clone(),
+            layer,
+        }
+    }
+
+    /// Reader starts from oldest buffered event
+    pub fn reader(&self) -> EventReader<T> {
+        EventReader {
+            events: self.clone(),
+            cursor: self.buffer.lock().previous_start,
+        }
+    }
+
+    /// Layers with alive writers
+    pub fn writers(&self) -> Vec<String> {
+        let mut writers = self.writers.lock().keys().cloned().collect::<Vec<_>>();
+        writers.sort();
+        writers
+    }
+
+    /// Buffered events count, for current and previous frames
+    pub fn len(&self) -> usize {
+        let buffer = self.buffer.lock();
+        buffer.previous.len() + buffer.current.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Send events to `Events<T>`, may be moved into scheduled task
+pub struct EventWriter<T> {
+    events: Events<T>,
+    layer: String,
+}
+
+impl<T> EventWriter<T> {
+    pub fn send(&self, event: T) {
+        self.events.buffer.lock().current.push(event);
+    }
+
+    pub fn send_batch(&self, events: impl IntoIterator<Item = T>) {
+        self.events.buffer.lock().current.extend(events);
+    }
+
+    pub fn layer(&self) -> &str {
+        &self.layer
+    }
+}
+
+impl<T> Clone for EventWriter<T> {
+    fn clone(&self) -> Self {
+        *self.events.writers.lock().entry(self.layer.clone()).or_default() += 1;
+
+        Self {
+            events: self.events.clone(),
+            layer: self.layer.clone(),
+        }
+    }
+}
+
+impl<T> Drop for EventWriter<T> {
+    fn drop(&mut self) {
+        let mut writers = self.events.writers.lock();
+
+        if let Some(count) = writers.get_mut(&self.layer) {
+            *count -= 1;
+
+            if *count == 0 {
+                writers.remove(&self.layer);
+            }
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for EventWriter<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventWriter")
+            .field("type", &std::any::type_name::<T>())
+            .field("layer", &self.layer)
+            .finish()
+    }
+}
+
+/// Read events with own cursor, every event read once by every reader
+pub struct EventReader<T> {
+    events: Events<T>,
+    cursor: u64,
+}
+
+impl<T: Clone> EventReader<T> {
+    /// New events since last read
+    pub fn read(&mut self) -> Vec<T> {
+        let buffer = self.events.buffer.lock();
+
+        if self.cursor < buffer.previous_start {
+            tracing::debug!(
+                "{count} {ty} events dropped before read",
+                count = buffer.previous_start - self.cursor,
+                ty = std::any::type_name::<T>()
+            );
+        }
+
+        let start = self.cursor.max(buffer.previous_start);
+        let current_start = buffer.current_start();
+
+        let previous = buffer.previous.iter().skip((start.min(current_start) - buffer.previous_start) as usize);
+        let current = buffer.current.iter().skip((start.max(current_start) - current_start) as usize);
+
+        let events = previous.chain(current).cloned().collect();
+
+        self.cursor = buffer.end();
+
+        events
+    }
+}
+
+impl<T> EventReader<T> {
+    /// Not read events count
+    pub fn len(&self) -> usize {
+        let buffer = self.events.buffer.lock();
+        (buffer.end() - self.cursor.max(buffer.previous_start)) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Skip all buffered events
+    pub fn clear(&mut self) {
+        self.cursor = self.events.buffer.lock().end();
+    }
+}
+
+impl<T> std::fmt::Debug for EventReader<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventReader")
+            .field("type", &std::any::type_name::<T>())
+            .field("cursor", &self.cursor)
+            .finish()
+    }
+}
+
+/// Task depends on tasks of layers with alive writers, not existing or disabled writers ignored
+impl<T> From<&Events<T>> for Dependency<'static, 0> {
+    fn from(value: &Events<T>) -> Self {
+        Dependency::Writers(value.writers())
+    }
+}
+
+impl<T> From<&EventReader<T>> for Dependency<'static, 0> {
+    fn from(value: &EventReader<T>) -> Self {
+        Dependency::Writers(value.events.writers())
+    }
+}
+
+type EventsUpdate = Box<dyn Fn() + Send + Sync>;
+
+/// Swap buffers of all registered events once per root stack frame
+#[derive(Clone, Default)]
+pub struct EventsRegistry {
+    updates: Arc<Mutex<Vec<EventsUpdate>>>,
+    in_frame: Arc<AtomicBool>,
+}
+
+impl std::fmt::Debug for EventsRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventsRegistry")
+            .field("events", &self.updates.lock().len())
+            .finish()
+    }
+}
+
+impl EventsRegistry {
+    pub fn new(_: ServiceProvider) -> ServiceBuildResult<Self> {
+        Ok(Default::default())
+    }
+
+    fn add(&self, update: EventsUpdate) {
+        self.updates.lock().push(update);
+    }
+
+    /// Return false if frame already started by parent stack
+    pub(crate) fn begin_frame(&self) -> bool {
+        if self.in_frame.swap(true, Ordering::AcqRel) {
+            return false;
+        }
+
+        for update in self.updates.lock().iter() {
+            update();
+        }
+
+        true
+    }
+
+    pub(crate) fn end_frame(&self) {
+        self.in_frame.store(false, Ordering::Release);
+    }
+}
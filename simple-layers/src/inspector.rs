@@ -0,0 +1,386 @@
+#[cfg(unix)]
+use std::{os::unix::net::UnixListener, path::PathBuf};
+use std::{
+    fmt::Display,
+    io::{BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener},
+    str::FromStr,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+    },
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::{
+    layer::LayersStack,
+    profiler::{FrameProfiler, chrome_trace},
+    stage::Stage,
+    stats::FrameStatsSummary,
+    types::error::{InspectorError, LayersStackError},
+};
+
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Inspector listen address, parsed from loopback socket address or `unix:<path>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InspectorEndpoint {
+    Tcp(SocketAddr),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl FromStr for InspectorEndpoint {
+    type Err = InspectorError;
+
+    fn from_str(endpoint: &str) -> Result<Self, Self::Err> {
+        #[cfg(unix)]
+        if let Some(path) = endpoint.strip_prefix("unix:") {
+            return Ok(Self::Unix(PathBuf::from(path)));
+        }
+
+        let endpoint = endpoint.parse().map(Self::Tcp).map_err(|_| InspectorError::InvalidEndpoint {
+            endpoint: endpoint.to_string(),
+        })?;
+
+        endpoint.check_loopback()?;
+
+        Ok(endpoint)
+    }
+}
+
+impl InspectorEndpoint {
+    /// Inspector has no authentication, so TCP endpoint accepts only local connections
+    fn check_loopback(&self) -> Result<(), InspectorError> {
+        match self {
+            Self::Tcp(addr) if !addr.ip().is_loopback() => Err(InspectorError::NotLoopback {
+                endpoint: self.to_string(),
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Display for InspectorEndpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Tcp(addr) => addr.fmt(f),
+            #[cfg(unix)]
+            Self::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// Inspector request line, for example `{"cmd":"disable","name":"debug_shape"}`.
+/// Every request answered by line `{"ok":true,"result":...}` or `{"ok":false,"error":"..."}`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum InspectorCommand {
+    /// Layers in update order
+    Layers,
+    Enable { name: String },
+    Disable { name: String },
+    /// Frame stats summary
+    Stats,
+    /// Profile next frame, answered by Chrome `trace_event` JSON after frame end
+    Trace,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LayerStateKind {
+    Pending,
+    Created,
+    Shutdown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LayerInfo {
+    pub id: String,
+    pub name: String,
+    pub state: LayerStateKind,
+    pub enabled: bool,
+    /// Layer and layer group enabled
+    pub active: bool,
+    pub group: Option<String>,
+    pub stage: Stage,
+    pub ty: &'static str,
+}
+
+#[derive(Debug)]
+pub(crate) struct InspectorRequest {
+    command: InspectorCommand,
+    reply: Sender<String>,
+}
+
+/// Stack control endpoint, requests received by connection threads and handled on stack update
+#[derive(Debug)]
+pub struct StackInspector {
+    endpoint: InspectorEndpoint,
+    requests: Receiver<InspectorRequest>,
+    stop: Arc<AtomicBool>,
+    profiler: FrameProfiler,
+    /// Replies waiting for traced frame end
+    traces: Vec<Sender<String>>,
+    /// Profiler enabled state before traced frame
+    profiler_enabled: bool,
+}
+
+impl StackInspector {
+    /// Bind endpoint, TCP port 0 replaced by bound port
+    pub(crate) fn bind(endpoint: InspectorEndpoint, profiler: FrameProfiler) -> Result<Self, InspectorError> {
+        endpoint.check_loopback()?;
+
+        let bind_err = |err: std::io::Error| InspectorError::Bind {
+            endpoint: endpoint.to_string(),
+            message: err.to_string(),
+        };
+
+        let (listener, bound) = match &endpoint {
+            InspectorEndpoint::Tcp(addr) => {
+                let listener = TcpListener::bind(addr).map_err(bind_err)?;
+                let bound = InspectorEndpoint::Tcp(listener.local_addr().map_err(bind_err)?);
+
+                (Listener::Tcp(listener), bound)
+            }
+            #[cfg(unix)]
+            InspectorEndpoint::Unix(path) => (
+                Listener::Unix(UnixListener::bind(path).map_err(bind_err)?),
+                endpoint.clone(),
+            ),
+        };
+
+        listener.set_nonblocking().map_err(bind_err)?;
+
+        let (sender, requests) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let listener_stop = stop.clone();
+
+        std::thread::Builder::new()
+            .name("layers-inspector".to_string())
+            .spawn(move || listener.run(sender, listener_stop))
+            .map_err(bind_err)?;
+
+        tracing::info!("Layers inspector listening on [{bound}]");
+
+        Ok(Self {
+            endpoint: bound,
+            requests,
+            stop,
+            profiler,
+            traces: Vec::new(),
+            profiler_enabled: false,
+        })
+    }
+
+    pub fn endpoint(&self) -> &InspectorEndpoint {
+        &self.endpoint
+    }
+
+    /// Answer received requests, called before stack frame
+    pub(crate) fn handle_requests(&mut self, stack: &mut LayersStack) {
+        let requests = self.requests.try_iter().collect::<Vec<_>>();
+
+        for InspectorRequest { command, reply } in requests {
+            let res = match command {
+                InspectorCommand::Layers => Ok(json!(layer_infos(stack))),
+                InspectorCommand::Enable { name } => switch_layer(stack, &name, true),
+                InspectorCommand::Disable { name } => switch_layer(stack, &name, false),
+                InspectorCommand::Stats => Ok(stats_json(&stack.stats().summary())),
+                InspectorCommand::Trace => {
+                    if self.traces.is_empty() {
+                        self.profiler_enabled = self.profiler.enabled();
+                        self.profiler.set_enabled(true);
+                    }
+
+                    self.traces.push(reply);
+                    continue;
+                }
+            };
+
+            _ = reply.send(response(res));
+        }
+    }
+
+    /// Answer trace requests, called after stack frame
+    pub(crate) fn end_frame(&mut self) {
+        if self.traces.is_empty() {
+            return;
+        }
+
+        let res = match self.profiler.last_frame() {
+            Some(frame) => Ok(chrome_trace(&[frame])),
+            None => Err("Traced frame not recorded".to_string()),
+        };
+
+        let response = response(res);
+
+        for reply in self.traces.drain(..) {
+            _ = reply.send(response.clone());
+        }
+
+        self.profiler.set_enabled(self.profiler_enabled);
+    }
+}
+
+impl Drop for StackInspector {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+
+        #[cfg(unix)]
+        if let InspectorEndpoint::Unix(path) = &self.endpoint {
+            _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+pub fn layer_infos(stack: &LayersStack) -> Vec<LayerInfo> {
+    stack
+        .layers()
+        .map(|layer| LayerInfo {
+            id: layer.id().to_string(),
+            name: layer.name().to_string(),
+            state: match (layer.is_created(), layer.is_shutdown()) {
+                (true, _) => LayerStateKind::Created,
+                (false, true) => LayerStateKind::Shutdown,
+                (false, false) => LayerStateKind::Pending,
+            },
+            enabled: layer.enabled(),
+            active: layer.active(),
+            group: layer.group().map(ToString::to_string),
+            stage: layer.stage(),
+            ty: layer.ty().name,
+        })
+        .collect()
+}
+
+fn switch_layer(stack: &mut LayersStack, name: &str, enabled: bool) -> Result<Value, String> {
+    let Some(id) = stack.get_layer(name).map(|layer| layer.id()) else {
+        return Err(LayersStackError::LayerNotFound { layer: name.to_string() }.to_string());
+    };
+
+    match enabled {
+        true => stack.enable(id),
+        false => stack.disable(id),
+    }
+
+    Ok(Value::Null)
+}
+
+fn stats_json(summary: &FrameStatsSummary) -> Value {
+    let ms = |dt: chrono::TimeDelta| dt.num_microseconds().unwrap_or(i64::MAX) as f64 / 1000.0;
+
+    json!({
+        "frame": summary.frame,
+        "fps": summary.fps,
+        "dt_ms": ms(summary.dt),
+        "smoothed_dt_ms": ms(summary.smoothed_dt),
+        "min_dt_ms": ms(summary.min_dt),
+        "max_dt_ms": ms(summary.max_dt),
+        "p50_ms": ms(summary.p50),
+        "p95_ms": ms(summary.p95),
+        "p99_ms": ms(summary.p99),
+    })
+}
+
+fn response(res: Result<Value, String>) -> String {
+    match res {
+        Ok(result) => json!({ "ok": true, "result": result }),
+        Err(error) => json!({ "ok": false, "error": error }),
+    }
+    .to_string()
+}
+
+type Connection = (Box<dyn Read + Send>, Box<dyn Write + Send>);
+
+enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+impl Listener {
+    fn set_nonblocking(&self) -> std::io::Result<()> {
+        match self {
+            Self::Tcp(listener) => listener.set_nonblocking(true),
+            #[cfg(unix)]
+            Self::Unix(listener) => listener.set_nonblocking(true),
+        }
+    }
+
+    fn accept(&self) -> std::io::Result<Connection> {
+        match self {
+            Self::Tcp(listener) => {
+                let (stream, _) = listener.accept()?;
+                stream.set_nonblocking(false)?;
+
+                Ok((Box::new(stream.try_clone()?), Box::new(stream)))
+            }
+            #[cfg(unix)]
+            Self::Unix(listener) => {
+                let (stream, _) = listener.accept()?;
+                stream.set_nonblocking(false)?;
+
+                Ok((Box::new(stream.try_clone()?), Box::new(stream)))
+            }
+        }
+    }
+
+    /// Accept connections until inspector dropped
+    fn run(self, requests: Sender<InspectorRequest>, stop: Arc<AtomicBool>) {
+        while !stop.load(Ordering::Relaxed) {
+            match self.accept() {
+                Ok(connection) => {
+                    let requests = requests.clone();
+                    std::thread::spawn(move || serve(connection, requests));
+                }
+                Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => {
+                    std::thread::sleep(ACCEPT_POLL_INTERVAL);
+                }
+                Err(err) => {
+                    tracing::warn!("Layers inspector accept failed: {err}");
+                    std::thread::sleep(ACCEPT_POLL_INTERVAL);
+                }
+            }
+        }
+    }
+}
+
+/// Serve connection requests until connection closed or inspector dropped
+fn serve((reader, mut writer): Connection, requests: Sender<InspectorRequest>) {
+    for line in BufReader::new(reader).lines() {
+        let Ok(line) = line else {
+            return;
+        };
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<InspectorCommand>(&line) {
+            Ok(command) => {
+                let (reply, answer) = mpsc::channel();
+
+                if requests.send(InspectorRequest { command, reply }).is_err() {
+                    return;
+                }
+
+                let Ok(answer) = answer.recv() else {
+                    return;
+                };
+
+                answer
+            }
+            Err(err) => response(Err(err.to_string())),
+        };
+
+        if writeln!(writer, "{response}").and_then(|_| writer.flush()).is_err() {
+            return;
+        }
+    }
+}
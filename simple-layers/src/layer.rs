@@ -1,26 +1,67 @@
-use std::{collections::HashMap, fmt::Debug, sync::Arc};
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, VecDeque},
+    fmt::{Debug, Display},
+    sync::Arc,
+    time::Instant,
+};
 
 use chrono::{DateTime, TimeDelta, Utc};
 use parking_lot::RwLock;
+use tracing::Instrument;
 use xdi::{ServiceProvider, types::error::ServiceBuildResult};
 
 use crate::{
+    background::BackgroundServiceInfo,
+    condition::RunCondition,
+    events::EventsRegistry,
+    profiler::FrameProfiler,
     scheduler::LayerScheduler,
+    stage::Stage,
+    stats::FrameStats,
+    time::{Clock, FixedTimeState, FixedTimestep},
     types::{
+        error::{LayerError, LayerErrorStage, LayersStackError},
         id::LayerId,
         type_info::{TypeInfo, TypeInfoSource},
     },
 };
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{
+    inspector::{InspectorEndpoint, StackInspector},
+    types::error::InspectorError,
+};
 
 #[derive(Debug)]
 pub struct LayersStack {
     scheduler: LayerScheduler,
 
     layers_order: Vec<LayerId>,
+    /// Layers order should be sorted by stages and constraints before next update
+    order_dirty: bool,
     layers_map: HashMap<LayerId, Layer, ahash::RandomState>,
     layer_name_to_id: HashMap<String, LayerId, ahash::RandomState>,
 
     last_update: Option<DateTime<Utc>>,
+    clock: Clock,
+
+    error_callback: Option<LayerErrorCallback>,
+
+    fixed_time: FixedTimeState,
+
+    /// Position for `push_layer` while source registered by `register_source_at`
+    insert_cursor: Option<usize>,
+
+    groups: HashMap<String, LayersGroup, ahash::RandomState>,
+    /// Group for new layers while group registered by `push_group`
+    current_group: Option<String>,
+
+    profiler: FrameProfiler,
+    stats: FrameStats,
+    events: EventsRegistry,
+
+    #[cfg(not(target_arch = "wasm32"))]
+    inspector: Option<StackInspector>,
 
     sp: ServiceProvider,
 }
@@ -30,9 +71,21 @@ impl LayersStack {
         Ok(Self {
             scheduler: sp.resolve()?,
             layers_order: Default::default(),
+            order_dirty: false,
             layers_map: Default::default(),
             layer_name_to_id: Default::default(),
             last_update: None,
+            clock: sp.resolve()?,
+            error_callback: None,
+            fixed_time: sp.resolve()?,
+            insert_cursor: None,
+            groups: Default::default(),
+            current_group: None,
+            profiler: sp.resolve()?,
+            stats: sp.resolve()?,
+            events: sp.resolve()?,
+            #[cfg(not(target_arch = "wasm32"))]
+            inspector: None,
             sp,
         })
     }
@@ -47,51 +100,692 @@ impl LayersStack {
     ) -> &mut Layer {
         let name = name.into();
 
-        let layer = Layer::new(name.clone(), layer_ctr, true);
+        if self.layer_name_to_id.contains_key(&name) {
+            tracing::warn!("[{name}] Layer name already registered, name lookup will return new layer");
+        }
+
+        let index = self.insert_cursor.unwrap_or(self.layers_order.len());
+
+        let layer = self.new_layer(name, layer_ctr);
+
+        self.attach_layer(index, layer)
+    }
+
+    /// Insert layer at position, layer name should be unique.
+    /// Position applies inside layer stage, layers sorted by stages before update
+    pub fn insert_layer<
+        TLayer: ILayer + 'static,
+        TCtr: Fn(ServiceProvider) -> anyhow::Result<TLayer> + 'static,
+    >(
+        &mut self,
+        position: LayerPosition<'_>,
+        name: impl Into<String>,
+        layer_ctr: TCtr,
+    ) -> Result<&mut Layer, LayersStackError> {
+        let name = name.into();
+
+        if self.layer_name_to_id.contains_key(&name) {
+            return Err(LayersStackError::DuplicateLayerName { name });
+        }
+
+        let index = self.position_index(position)?;
+
+        let layer = self.new_layer(name, layer_ctr);
+
+        Ok(self.attach_layer(index, layer))
+    }
+
+    pub fn insert_before<
+        'a,
+        TLayer: ILayer + 'static,
+        TCtr: Fn(ServiceProvider) -> anyhow::Result<TLayer> + 'static,
+    >(
+        &mut self,
+        target: impl Into<LayerKey<'a>>,
+        name: impl Into<String>,
+        layer_ctr: TCtr,
+    ) -> Result<&mut Layer, LayersStackError> {
+        self.insert_layer(LayerPosition::Before(target.into()), name, layer_ctr)
+    }
+
+    pub fn insert_after<
+        'a,
+        TLayer: ILayer + 'static,
+        TCtr: Fn(ServiceProvider) -> anyhow::Result<TLayer> + 'static,
+    >(
+        &mut self,
+        target: impl Into<LayerKey<'a>>,
+        name: impl Into<String>,
+        layer_ctr: TCtr,
+    ) -> Result<&mut Layer, LayersStackError> {
+        self.insert_layer(LayerPosition::After(target.into()), name, layer_ctr)
+    }
+
+    /// Remove layer from stack, `on_detach` hook called if layer already created
+    pub fn remove_layer<'a>(&mut self, layer: impl Into<LayerKey<'a>>) -> Result<(), LayersStackError> {
+        let id = self.layer_id(layer.into())?;
+
+        let mut layer = self.detach_layer(id);
+
+        layer.detach(&self.sp, &mut self.scheduler);
+
+        self.scheduler.cancel_tasks_of(id);
+
+        self.fixed_time.remove(id);
+
+        tracing::debug!("[{name}] <{id}> Layer removed", name = layer.name());
+
+        Ok(())
+    }
+
+    /// Move layer to new position, layer state preserved.
+    /// Layer can't be moved relative to layer of other stage, `First` and `Last` apply inside stage
+    pub fn move_layer<'a>(
+        &mut self,
+        layer: impl Into<LayerKey<'a>>,
+        position: LayerPosition<'_>,
+    ) -> Result<(), LayersStackError> {
+        let id = self.layer_id(layer.into())?;
+
+        self.check_move_stage(id, position)?;
+
+        // Target resolved before layer detached, position relative to moved layer itself keeps it in place
+        let mut index = self.position_index(position)?;
+
+        if self.layers_order.iter().position(|x| *x == id).unwrap() < index {
+            index -= 1;
+        }
+
+        let layer = self.detach_layer(id);
+
+        self.attach_layer(index, layer);
+
+        Ok(())
+    }
+
+    pub fn get_layer<'a>(&self, layer: impl Into<LayerKey<'a>>) -> Option<&Layer> {
+        let id = self.layer_id(layer.into()).ok()?;
+        self.layers_map.get(&id)
+    }
+
+    /// Layer stage and constraints may be changed, layers sorted again before next update
+    pub fn get_layer_mut<'a>(&mut self, layer: impl Into<LayerKey<'a>>) -> Option<&mut Layer> {
+        let id = self.layer_id(layer.into()).ok()?;
+        self.layers_map.get_mut(&id)
+    }
+
+    /// Layers in update order, order sorted by stages on `sort_layers` or next update
+    pub fn layers(&self) -> impl Iterator<Item = &Layer> {
+        self.layers_order.iter().map(|id| &self.layers_map[id])
+    }
+
+    pub fn register_source<TLayersSource: ILayersSource>(&mut self) {
+        TLayersSource::register(self);
+    }
+
+    /// Register source layers starting from position, instead of stack end
+    pub fn register_source_at<TLayersSource: ILayersSource>(
+        &mut self,
+        position: LayerPosition<'_>,
+    ) -> Result<(), LayersStackError> {
+        let index = self.position_index(position)?;
+
+        let prev_cursor = self.insert_cursor.replace(index);
+
+        TLayersSource::register(self);
+
+        self.insert_cursor = prev_cursor;
+
+        Ok(())
+    }
+
+    /// Register layers pushed or inserted by `register` as named group, group can be extended by next call
+    pub fn push_group(&mut self, name: impl Into<String>, register: impl FnOnce(&mut Self)) {
+        let name = name.into();
+
+        self.groups.entry(name.clone()).or_default();
+
+        let prev_group = self.current_group.replace(name);
+
+        register(self);
+
+        self.current_group = prev_group;
+    }
+
+    /// Register source layers as named group
+    pub fn register_source_group<TLayersSource: ILayersSource>(&mut self, name: impl Into<String>) {
+        self.push_group(name, TLayersSource::register);
+    }
+
+    /// Group layers in update order
+    pub fn group_layers<'a>(&'a self, group: &'a str) -> impl Iterator<Item = &'a Layer> {
+        self.layers().filter(move |layer| layer.group() == Some(group))
+    }
+
+    pub fn group_enabled(&self, group: &str) -> Option<bool> {
+        self.groups.get(group).map(|group| group.enabled)
+    }
+
+    /// Enable group, layers disabled by itself stay disabled
+    pub fn enable_group(&mut self, group: &str) -> Result<(), LayersStackError> {
+        self.switch_group(group, true)
+    }
+
+    /// Disable all group layers, layers own `enabled` flag preserved
+    pub fn disable_group(&mut self, group: &str) -> Result<(), LayersStackError> {
+        self.switch_group(group, false)
+    }
+
+    fn switch_group(&mut self, group: &str, enabled: bool) -> Result<(), LayersStackError> {
+        let Some(state) = self.groups.get_mut(group) else {
+            return Err(LayersStackError::GroupNotFound { group: group.to_string() });
+        };
+
+        if state.enabled == enabled {
+            return Ok(());
+        }
+
+        state.enabled = enabled;
+
+        for layer_id in &self.layers_order {
+            let layer = self.layers_map.get_mut(layer_id).unwrap();
+
+            if layer.group() == Some(group) {
+                layer.switch_group(enabled, &self.sp, &mut self.scheduler);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Move all group layers to position, layers relative order preserved.
+    /// Same stage rules as `move_layer` apply to every group layer
+    pub fn move_group(&mut self, group: &str, position: LayerPosition<'_>) -> Result<(), LayersStackError> {
+        if !self.groups.contains_key(group) {
+            return Err(LayersStackError::GroupNotFound { group: group.to_string() });
+        }
+
+        let group_indexes = self
+            .layers_order
+            .iter()
+            .enumerate()
+            .filter(|(_, id)| self.layers_map[*id].group() == Some(group))
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+
+        for index in &group_indexes {
+            self.check_move_stage(self.layers_order[*index], position)?;
+        }
+
+        let group_layers = group_indexes
+            .iter()
+            .rev()
+            .map(|index| self.detach_layer(self.layers_order[*index]))
+            .collect::<Vec<_>>();
+
+        // Position may point to group layer
+        let index = match self.position_index(position) {
+            Ok(index) => index,
+            Err(err) => {
+                for (index, layer) in group_indexes.into_iter().zip(group_layers.into_iter().rev()) {
+                    self.attach_layer(index, layer);
+                }
+
+                return Err(err);
+            }
+        };
+
+        for layer in group_layers {
+            self.attach_layer(index, layer);
+        }
+
+        Ok(())
+    }
+
+    /// Remove group and all group layers
+    pub fn remove_group(&mut self, group: &str) -> Result<(), LayersStackError> {
+        if self.groups.remove(group).is_none() {
+            return Err(LayersStackError::GroupNotFound { group: group.to_string() });
+        }
+
+        let group_layers = self.group_layers(group).map(|layer| layer.id()).collect::<Vec<_>>();
+
+        for layer_id in group_layers.into_iter().rev() {
+            self.remove_layer(layer_id)?;
+        }
+
+        Ok(())
+    }
+
+    /// Attach layer built outside of stack, layer name should be unique
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn attach_plugin_layer(
+        &mut self,
+        position: LayerPosition<'_>,
+        group: Option<String>,
+        mut layer: Layer,
+    ) -> Result<&mut Layer, LayersStackError> {
+        if self.layer_name_to_id.contains_key(layer.name()) {
+            return Err(LayersStackError::DuplicateLayerName {
+                name: layer.name().to_string(),
+            });
+        }
+
+        let index = self.position_index(position)?;
+
+        if let Some(group) = group {
+            layer.group_enabled = self.groups.entry(group.clone()).or_default().enabled;
+            layer.group = Some(group);
+        }
+
+        Ok(self.attach_layer(index, layer))
+    }
+
+    fn new_layer<
+        TLayer: ILayer + 'static,
+        TCtr: Fn(ServiceProvider) -> anyhow::Result<TLayer> + 'static,
+    >(
+        &self,
+        name: String,
+        layer_ctr: TCtr,
+    ) -> Layer {
+        let mut layer = Layer::new(name, layer_ctr, true);
+
+        if let Some(group) = &self.current_group {
+            layer.group = Some(group.clone());
+            layer.group_enabled = self.groups[group].enabled;
+        }
+
+        layer
+    }
+
+    fn layer_id(&self, layer: LayerKey<'_>) -> Result<LayerId, LayersStackError> {
+        match layer {
+            LayerKey::Id(id) if self.layers_map.contains_key(&id) => Ok(id),
+            LayerKey::Name(name) if self.layer_name_to_id.contains_key(name) => Ok(self.layer_name_to_id[name]),
+            layer => Err(LayersStackError::LayerNotFound { layer: layer.to_string() }),
+        }
+    }
+
+    fn position_index(&self, position: LayerPosition<'_>) -> Result<usize, LayersStackError> {
+        let index_of = |layer| {
+            let id = self.layer_id(layer)?;
+            Ok(self.layers_order.iter().position(|x| *x == id).unwrap())
+        };
+
+        match position {
+            LayerPosition::First => Ok(0),
+            LayerPosition::Last => Ok(self.layers_order.len()),
+            LayerPosition::Before(layer) => index_of(layer),
+            LayerPosition::After(layer) => index_of(layer).map(|index| index + 1),
+        }
+    }
+
+    /// Stages sort would undo move next to layer of other stage
+    fn check_move_stage(&self, id: LayerId, position: LayerPosition<'_>) -> Result<(), LayersStackError> {
+        let (LayerPosition::Before(target) | LayerPosition::After(target)) = position else {
+            return Ok(());
+        };
 
+        let layer = &self.layers_map[&id];
+        let target = &self.layers_map[&self.layer_id(target)?];
+
+        if layer.stage != target.stage {
+            return Err(LayersStackError::CrossStageMove {
+                layer: layer.name().to_string(),
+                target: target.name().to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn attach_layer(&mut self, index: usize, layer: Layer) -> &mut Layer {
         let layer_id = layer.id();
 
-        self.layers_order.push(layer_id);
+        self.order_dirty = true;
+
+        if let Some(cursor) = &mut self.insert_cursor
+            && index <= *cursor
+        {
+            *cursor += 1;
+        }
+
+        self.layers_order.insert(index, layer_id);
+        self.layer_name_to_id.insert(layer.name().to_string(), layer_id);
         self.layers_map.insert(layer_id, layer);
-        self.layer_name_to_id.insert(name, layer_id);
 
         self.layers_map.get_mut(&layer_id).unwrap()
     }
 
-    pub fn register_source<TLayersSource: ILayersSource>(&mut self) {
-        TLayersSource::register(self);
+    fn detach_layer(&mut self, id: LayerId) -> Layer {
+        let index = self.layers_order.iter().position(|x| *x == id).unwrap();
+
+        if let Some(cursor) = &mut self.insert_cursor
+            && index < *cursor
+        {
+            *cursor -= 1;
+        }
+
+        self.layers_order.remove(index);
+
+        let layer = self.layers_map.remove(&id).unwrap();
+
+        if self.layer_name_to_id.get(layer.name()) == Some(&id) {
+            self.layer_name_to_id.remove(layer.name());
+        }
+
+        layer
+    }
+
+    /// Set callback called on every layer error, before error policy applied
+    pub fn set_error_callback(&mut self, callback: impl Fn(&LayerError) + 'static) {
+        self.error_callback = Some(LayerErrorCallback(Box::new(callback)));
+    }
+
+    /// Last layer errors, oldest first
+    pub fn layer_errors(&self, id: LayerId) -> Option<&VecDeque<LayerError>> {
+        self.layers_map.get(&id).map(|layer| layer.errors())
+    }
+
+    pub fn scheduler(&self) -> &LayerScheduler {
+        &self.scheduler
+    }
+
+    pub fn scheduler_mut(&mut self) -> &mut LayerScheduler {
+        &mut self.scheduler
+    }
+
+    pub fn profiler(&self) -> &FrameProfiler {
+        &self.profiler
+    }
+
+    /// Opt-in frames pipelining, `Stage::Render` layers tasks of up to `depth` frames overlap next frames updates.
+    /// Render layers should take data through `Snapshot` instead of reading shared state in tasks
+    pub fn set_pipeline_depth(&mut self, depth: usize) {
+        self.scheduler.set_pipeline_depth(depth);
+    }
+
+    pub fn stats(&self) -> &FrameStats {
+        &self.stats
+    }
+
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    /// Open control endpoint, previous endpoint closed. Requests handled on next updates
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn open_inspector(&mut self, endpoint: InspectorEndpoint) -> Result<&StackInspector, InspectorError> {
+        self.inspector = None;
+
+        Ok(self
+            .inspector
+            .insert(StackInspector::bind(endpoint, self.profiler.clone())?))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn close_inspector(&mut self) {
+        self.inspector = None;
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn inspector(&self) -> Option<&StackInspector> {
+        self.inspector.as_ref()
+    }
+
+    /// Background services of stack layers
+    pub fn background_services(&self) -> Vec<BackgroundServiceInfo> {
+        self.scheduler.background_services()
     }
 
-    pub fn update(&mut self) {
-        let last_update = self.last_update.unwrap_or_else(|| Utc::now());
-        let now = Utc::now();
+    /// Update all enabled layers and wait scheduled tasks.
+    /// Return error if failed layer has `LayerErrorPolicy::Abort` policy (rest layers skipped in that case)
+    /// or if strict scheduler found dependency problems
+    pub fn update(&mut self) -> Result<(), LayersStackError> {
+        let now = self.clock.now();
+        let last_update = self.last_update.unwrap_or(now);
 
         let dt = now - last_update;
 
+        self.last_update = Some(now);
+
+        self.update_with_dt(&dt)
+    }
+
+    /// Update layers with exactly `dt`, manual clock advanced by `dt`
+    pub fn step(&mut self, dt: TimeDelta) -> Result<(), LayersStackError> {
+        self.clock.advance(dt);
+        self.last_update = Some(self.clock.now());
+
+        self.update_with_dt(&dt)
+    }
+
+    /// Run `count` frames by `step`, stopped on first error
+    pub fn run_frames(&mut self, count: usize, dt: TimeDelta) -> Result<(), LayersStackError> {
+        for _ in 0..count {
+            self.step(dt)?;
+        }
+
+        Ok(())
+    }
+
+    /// Sort layers by stages and `before`/`after` constraints, constraints on missing layers ignored.
+    /// Layers without constraints keep registration order inside stage
+    pub fn sort_layers(&mut self) -> Result<(), LayersStackError> {
+        if !self.order_dirty && !self.layers_map.values().any(|layer| layer.order_changed) {
+            return Ok(());
+        }
+
+        let index_of = self
+            .layers_order
+            .iter()
+            .enumerate()
+            .map(|(index, id)| (*id, index))
+            .collect::<HashMap<_, _, ahash::RandomState>>();
+
+        let mut dependents = vec![Vec::new(); self.layers_order.len()];
+        let mut deps_left = vec![0usize; self.layers_order.len()];
+
+        for (index, id) in self.layers_order.iter().enumerate() {
+            let layer = &self.layers_map[id];
+
+            let constraints = layer
+                .before
+                .iter()
+                .map(|target| (target, true))
+                .chain(layer.after.iter().map(|target| (target, false)));
+
+            for (target, before) in constraints {
+                let Some(target_id) = self.layer_name_to_id.get(target) else {
+                    tracing::debug!("[{name}] Layer order target [{target}] not found", name = layer.name());
+                    continue;
+                };
+
+                let target_layer = &self.layers_map[target_id];
+
+                // Stages order has priority over constraints
+                if target_layer.stage != layer.stage {
+                    if (layer.stage < target_layer.stage) != before {
+                        tracing::warn!(
+                            "[{name}] Layer order constraint on [{target}] conflicts with stages, ignored",
+                            name = layer.name()
+                        );
+                    }
+
+                    continue;
+                }
+
+                let target_index = index_of[target_id];
+
+                let (first, second) = if before { (index, target_index) } else { (target_index, index) };
+
+                dependents[first].push(second);
+                deps_left[second] += 1;
+            }
+        }
+
+        let stage_of = |index: usize| self.layers_map[&self.layers_order[index]].stage;
+
+        let mut ready = (0..self.layers_order.len())
+            .filter(|index| deps_left[*index] == 0)
+            .map(|index| Reverse((stage_of(index), index)))
+            .collect::<BinaryHeap<_>>();
+
+        let mut order = Vec::with_capacity(self.layers_order.len());
+
+        while let Some(Reverse((_, index))) = ready.pop() {
+            order.push(self.layers_order[index]);
+
+            for dependent in &dependents[index] {
+                deps_left[*dependent] -= 1;
+
+                if deps_left[*dependent] == 0 {
+                    ready.push(Reverse((stage_of(*dependent), *dependent)));
+                }
+            }
+        }
+
+        if order.len() < self.layers_order.len() {
+            let layers = (0..self.layers_order.len())
+                .filter(|index| deps_left[*index] > 0)
+                .map(|index| self.layers_map[&self.layers_order[index]].name().to_string())
+                .collect();
+
+            return Err(LayersStackError::OrderCycle { layers });
+        }
+
+        self.layers_order = order;
+        self.order_dirty = false;
+
+        for layer in self.layers_map.values_mut() {
+            layer.order_changed = false;
+        }
+
+        Ok(())
+    }
+
+    fn update_with_dt(&mut self, dt: &TimeDelta) -> Result<(), LayersStackError> {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(mut inspector) = self.inspector.take() {
+            inspector.handle_requests(self);
+            self.inspector = Some(inspector);
+        }
+
+        self.sort_layers()?;
+
+        // Nested stack layers recorded in parent stack frame
+        let profiler_frame = self.profiler.begin_frame();
+        let events_frame = self.events.begin_frame();
+        let stats_frame = self.stats.begin_frame(dt);
+
+        self.scheduler
+            .begin_frame(self.layers_order.iter().map(|id| &self.layers_map[id]));
+
+        let mut res = Ok(());
+
         for layer_id in &self.layers_order {
-            // TODO: add err handling
-            let layer = self.layers_map.get_mut(&layer_id).unwrap();
-            layer
-                .update(&self.sp, &dt, &mut self.scheduler)
-                .inspect_err(|e| tracing::error!("{e:?}"))
-                .unwrap();
+            let layer = self.layers_map.get_mut(layer_id).unwrap();
+
+            let update_start = Instant::now();
+
+            let update_res = layer.update(&self.sp, &self.clock, dt, &mut self.scheduler);
+
+            self.scheduler.layer_updated(layer.name());
+
+            if !matches!(update_res, Ok(false)) && self.profiler.enabled() {
+                self.profiler
+                    .record_layer(layer.id(), layer.name(), update_start, Instant::now());
+            }
+
+            if let Some(alpha) = layer.interpolation_alpha() {
+                self.fixed_time.set_alpha(layer.id(), alpha);
+            }
+
+            let Err(err) = update_res else {
+                continue;
+            };
+
+            if let Err(err) = layer.handle_error(err, self.error_callback.as_ref(), &self.sp, &mut self.scheduler) {
+                res = Err(err.into());
+                break;
+            }
         }
 
-        self.scheduler.wait_all_blocking();
+        self.scheduler.end_layers_update();
 
-        self.last_update = Some(now);
+        let schedule_res = self.scheduler.wait_frame_blocking();
+
+        for task_err in self.scheduler.take_task_errors() {
+            let Some(layer) = self.layers_map.get_mut(&task_err.layer_id) else {
+                tracing::error!("{task_err}");
+                continue;
+            };
+
+            let err = LayerError::new(
+                layer.id(),
+                layer.name().to_string(),
+                LayerErrorStage::Task,
+                self.clock.now(),
+                task_err.into(),
+            );
+
+            if let Err(err) = layer.handle_error(err, self.error_callback.as_ref(), &self.sp, &mut self.scheduler)
+                && res.is_ok()
+            {
+                res = Err(err.into());
+            }
+        }
+
+        if profiler_frame {
+            self.profiler.end_frame();
+        }
+
+        if events_frame {
+            self.events.end_frame();
+        }
+
+        if stats_frame {
+            self.stats.end_frame();
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(inspector) = &mut self.inspector {
+            inspector.end_frame();
+        }
+
+        res.and(schedule_res.map_err(Into::into))
     }
 
+    /// Enable layer, `on_enable` hook called if layer already created
     pub fn enable(&mut self, id: LayerId) {
         if let Some(layer) = self.layers_map.get_mut(&id) {
-            layer.enable();
+            layer.switch(true, &self.sp, &mut self.scheduler);
         }
     }
 
+    /// Disable layer, `on_disable` hook called if layer already created
     pub fn disable(&mut self, id: LayerId) {
         if let Some(layer) = self.layers_map.get_mut(&id) {
-            layer.disable();
+            layer.switch(false, &self.sp, &mut self.scheduler);
+        }
+    }
+
+    /// Call `on_shutdown` and `on_detach` hooks for all created layers in reverse order and wait hooks tasks.
+    /// Shut down layers are not created again by next updates
+    pub fn shutdown(&mut self) {
+        self.scheduler.wait_pipeline_blocking();
+
+        for layer_id in self.layers_order.iter().rev() {
+            let layer = self.layers_map.get_mut(layer_id).unwrap();
+            layer.shutdown(&self.sp, &mut self.scheduler);
         }
+
+        if let Err(err) = self.scheduler.wait_all_blocking() {
+            tracing::error!("{err}");
+        }
+
+        self.scheduler.wait_pipeline_blocking();
     }
 }
 
@@ -99,20 +793,143 @@ pub trait ILayer
 where
     Self: Debug,
 {
+    /// Called once after layer creation, before first `on_update`
+    #[allow(unused)]
+    fn on_attach(&mut self, scheduler: &mut LayerScheduler) {}
+
+    #[allow(unused)]
+    fn on_update(&mut self, dt: &TimeDelta, scheduler: &mut LayerScheduler) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Called from `LayersStack::enable` when layer was disabled
+    #[allow(unused)]
+    fn on_enable(&mut self, scheduler: &mut LayerScheduler) {}
+
+    /// Called from `LayersStack::disable` when layer was enabled
+    #[allow(unused)]
+    fn on_disable(&mut self, scheduler: &mut LayerScheduler) {}
+
+    /// Called when layer removed from stack, after `on_shutdown` on stack shutdown
+    #[allow(unused)]
+    fn on_detach(&mut self, scheduler: &mut LayerScheduler) {}
+
+    /// Called from `LayersStack::shutdown`, before `on_detach`
     #[allow(unused)]
-    fn on_update(&mut self, dt: &TimeDelta, scheduler: &mut LayerScheduler) {}
+    fn on_shutdown(&mut self, scheduler: &mut LayerScheduler) {}
+}
+
+/// Nested stack, child layers updated with parent dt and own scheduler scope.
+/// Child tasks awaited inside parent layer update, dependencies on parent layers unknown for child scheduler
+impl ILayer for LayersStack {
+    fn on_update(&mut self, dt: &TimeDelta, _scheduler: &mut LayerScheduler) -> anyhow::Result<()> {
+        self.update_with_dt(dt)?;
+        Ok(())
+    }
+
+    fn on_detach(&mut self, _scheduler: &mut LayerScheduler) {
+        self.shutdown();
+    }
 }
 
 pub trait ILayersSource {
     fn register(layers_stack: &mut LayersStack);
 }
 
+/// Layer reference by id or by name
+#[derive(Debug, Clone, Copy)]
+pub enum LayerKey<'a> {
+    Id(LayerId),
+    Name(&'a str),
+}
+
+impl Display for LayerKey<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LayerKey::Id(id) => write!(f, "{id}"),
+            LayerKey::Name(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+impl From<LayerId> for LayerKey<'_> {
+    fn from(value: LayerId) -> Self {
+        LayerKey::Id(value)
+    }
+}
+
+impl<'a> From<&'a str> for LayerKey<'a> {
+    fn from(value: &'a str) -> Self {
+        LayerKey::Name(value)
+    }
+}
+
+impl<'a> From<&'a String> for LayerKey<'a> {
+    fn from(value: &'a String) -> Self {
+        LayerKey::Name(value)
+    }
+}
+
+/// Layer position in stack update order
+#[derive(Debug, Clone, Copy)]
+pub enum LayerPosition<'a> {
+    First,
+    Last,
+    Before(LayerKey<'a>),
+    After(LayerKey<'a>),
+}
+
+/// Reaction on layer build or update error
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LayerErrorPolicy {
+    /// Log error and continue, failed build repeated on next update
+    #[default]
+    Log,
+    /// Disable layer
+    Disable,
+    /// Recreate layer on next update, layer disabled after `attempts` consecutive failures
+    Retry { attempts: usize },
+    /// Stop stack update and return error from `LayersStack::update`
+    Abort,
+}
+
+const LAYER_ERRORS_HISTORY_LEN: usize = 16;
+
+#[derive(Debug)]
+struct LayersGroup {
+    enabled: bool,
+}
+
+impl Default for LayersGroup {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
 #[derive(Debug)]
 pub struct Layer {
     ctr: LayerBuilder,
     state: LayerState,
     enabled: bool,
 
+    error_policy: LayerErrorPolicy,
+    errors: VecDeque<LayerError>,
+    failures: usize,
+
+    fixed_timestep: Option<FixedTimestep>,
+    run_conditions: Vec<RunCondition>,
+
+    stage: Stage,
+    /// Layers names in same stage updated after this layer
+    before: Vec<String>,
+    /// Layers names in same stage updated before this layer
+    after: Vec<String>,
+    /// Stage or constraints changed since last stack sort
+    order_changed: bool,
+
+    group: Option<String>,
+    group_enabled: bool,
+
     ty: TypeInfo,
     name: String,
     id: LayerId,
@@ -134,6 +951,17 @@ impl Layer {
             })),
             state: LayerState::Pending,
             enabled,
+            error_policy: Default::default(),
+            errors: Default::default(),
+            failures: 0,
+            fixed_timestep: None,
+            run_conditions: Vec::new(),
+            stage: Stage::default(),
+            before: Vec::new(),
+            after: Vec::new(),
+            order_changed: false,
+            group: None,
+            group_enabled: true,
             ty: TLayer::type_info(),
             name,
             id: LayerId::new(),
@@ -150,49 +978,232 @@ impl Layer {
         self
     }
 
+    pub fn with_error_policy(&mut self, policy: LayerErrorPolicy) -> &mut Self {
+        self.error_policy = policy;
+        self
+    }
+
+    /// Update layer with fixed dt, zero or more times per frame
+    pub fn with_fixed_timestep(&mut self, fixed_timestep: FixedTimestep) -> &mut Self {
+        self.fixed_timestep = Some(fixed_timestep);
+        self
+    }
+
+    pub fn with_stage(&mut self, stage: Stage) -> &mut Self {
+        self.stage = stage;
+        self.order_changed = true;
+        self
+    }
+
+    /// Update layer before other layer of same stage
+    pub fn before(&mut self, layer: impl Into<String>) -> &mut Self {
+        self.before.push(layer.into());
+        self.order_changed = true;
+        self
+    }
+
+    /// Update layer after other layer of same stage
+    pub fn after(&mut self, layer: impl Into<String>) -> &mut Self {
+        self.after.push(layer.into());
+        self.order_changed = true;
+        self
+    }
+
+    /// Update layer only in frames where all conditions true
+    pub fn with_run_condition(&mut self, condition: RunCondition) -> &mut Self {
+        self.run_conditions.push(condition);
+        self
+    }
+
     pub fn update(
         &mut self,
         sp: &ServiceProvider,
+        clock: &Clock,
         dt: &TimeDelta,
         scheduler: &mut LayerScheduler,
-    ) -> anyhow::Result<bool> {
-        if !self.enabled {
+    ) -> Result<bool, LayerError> {
+        if !self.active() || self.is_shutdown() {
             return Ok(false);
         }
 
-        let ctx = sp.resolve::<LayerCtx>().unwrap();
+        Self::set_ctx(sp, self.id, &self.name);
 
-        ctx.change(LayerCtxInner {
-            id: self.id,
-            name: self.name.clone(),
-        });
+        // All conditions checked to keep frames and time counters in sync
+        let mut run = true;
 
-        match &mut self.state {
-            LayerState::Pending => {
-                tracing::debug!("[{name}] <{id}> Layer first update", id = self.id, name = self.name);
+        for condition in &mut self.run_conditions {
+            run &= condition.check(sp, dt);
+        }
 
-                let mut service = self.ctr.build(sp.clone())?;
+        if !run {
+            return Ok(false);
+        }
 
-                tracing::debug!("[{name}] <{id}> Layer created", id = self.id, name = self.name);
+        if let LayerState::Pending = self.state {
+            tracing::debug!("[{name}] <{id}> Layer first update", id = self.id, name = self.name);
 
-                service.on_update(dt, scheduler);
+            let mut service = self
+                .ctr
+                .build(sp.clone())
+                .map_err(|e| LayerError::new(self.id, self.name.clone(), LayerErrorStage::Build, clock.now(), e))?;
 
-                tracing::debug!("[{name}] <{id}> Layer updated", id = self.id, name = self.name);
+            tracing::debug!("[{name}] <{id}> Layer created", id = self.id, name = self.name);
 
-                self.state = LayerState::Created { service };
-            }
-            LayerState::Created { service } => {
-                tracing::debug!("[{name}] <{id}> Layer update", id = self.id, name = self.name);
+            service.on_attach(scheduler);
 
-                service.on_update(dt, scheduler);
+            tracing::debug!("[{name}] <{id}> Layer attached", id = self.id, name = self.name);
 
-                tracing::debug!("[{name}] <{id}> Layer updated", id = self.id, name = self.name);
-            }
+            self.state = LayerState::Created { service };
         }
 
+        let LayerState::Created { service } = &mut self.state else {
+            unreachable!("Layer created above");
+        };
+
+        tracing::debug!("[{name}] <{id}> Layer update", id = self.id, name = self.name);
+
+        let (dt, steps) = match &mut self.fixed_timestep {
+            Some(fixed_timestep) => (fixed_timestep.step(), fixed_timestep.advance(dt)),
+            None => (*dt, 1),
+        };
+
+        for _ in 0..steps {
+            service
+                .on_update(&dt, scheduler)
+                .map_err(|e| LayerError::new(self.id, self.name.clone(), LayerErrorStage::Update, clock.now(), e))?;
+        }
+
+        tracing::debug!("[{name}] <{id}> Layer updated", id = self.id, name = self.name);
+
+        self.failures = 0;
+
         Ok(true)
     }
 
+    /// Record error in history and apply error policy, return error back if stack update should be aborted
+    pub(crate) fn handle_error(
+        &mut self,
+        err: LayerError,
+        callback: Option<&LayerErrorCallback>,
+        sp: &ServiceProvider,
+        scheduler: &mut LayerScheduler,
+    ) -> Result<(), LayerError> {
+        tracing::error!("{err}");
+
+        if let Some(callback) = callback {
+            callback.call(&err);
+        }
+
+        self.failures += 1;
+
+        if self.errors.len() == LAYER_ERRORS_HISTORY_LEN {
+            self.errors.pop_front();
+        }
+
+        self.errors.push_back(err.clone());
+
+        match self.error_policy {
+            LayerErrorPolicy::Log => {}
+            LayerErrorPolicy::Disable => {
+                self.switch(false, sp, scheduler);
+            }
+            LayerErrorPolicy::Retry { attempts } => {
+                if self.failures > attempts {
+                    tracing::warn!("[{name}] <{id}> Layer retry attempts exceeded", id = self.id, name = self.name);
+                    self.switch(false, sp, scheduler);
+                } else {
+                    self.detach(sp, scheduler);
+                }
+            }
+            LayerErrorPolicy::Abort => {
+                return Err(err);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn switch(&mut self, enabled: bool, sp: &ServiceProvider, scheduler: &mut LayerScheduler) {
+        let was_active = self.active();
+
+        self.enabled = enabled;
+
+        self.on_active_changed(was_active, sp, scheduler);
+    }
+
+    pub(crate) fn switch_group(&mut self, enabled: bool, sp: &ServiceProvider, scheduler: &mut LayerScheduler) {
+        let was_active = self.active();
+
+        self.group_enabled = enabled;
+
+        self.on_active_changed(was_active, sp, scheduler);
+    }
+
+    fn on_active_changed(&mut self, was_active: bool, sp: &ServiceProvider, scheduler: &mut LayerScheduler) {
+        let active = self.active();
+
+        if was_active == active {
+            return;
+        }
+
+        let LayerState::Created { service } = &mut self.state else {
+            return;
+        };
+
+        Self::set_ctx(sp, self.id, &self.name);
+
+        if active {
+            tracing::debug!("[{name}] <{id}> Layer enabled", id = self.id, name = self.name);
+            scheduler.resume_background_of(self.id);
+            service.on_enable(scheduler);
+        } else {
+            tracing::debug!("[{name}] <{id}> Layer disabled", id = self.id, name = self.name);
+            service.on_disable(scheduler);
+            scheduler.stop_background_of(self.id);
+        }
+    }
+
+    /// Call `on_shutdown` and `on_detach` hooks, layer not created again after shutdown
+    pub(crate) fn shutdown(&mut self, sp: &ServiceProvider, scheduler: &mut LayerScheduler) {
+        if let LayerState::Created { service } = &mut self.state {
+            Self::set_ctx(sp, self.id, &self.name);
+
+            service.on_shutdown(scheduler);
+
+            tracing::debug!("[{name}] <{id}> Layer shutdown", id = self.id, name = self.name);
+
+            self.detach(sp, scheduler);
+        }
+
+        self.state = LayerState::Shutdown;
+    }
+
+    /// Call `on_detach` and drop layer instance, layer will be created again on next update
+    pub(crate) fn detach(&mut self, sp: &ServiceProvider, scheduler: &mut LayerScheduler) {
+        let LayerState::Created { service } = &mut self.state else {
+            return;
+        };
+
+        Self::set_ctx(sp, self.id, &self.name);
+
+        service.on_detach(scheduler);
+
+        scheduler.remove_background_of(self.id);
+
+        tracing::debug!("[{name}] <{id}> Layer detached", id = self.id, name = self.name);
+
+        self.state = LayerState::Pending;
+    }
+
+    fn set_ctx(sp: &ServiceProvider, id: LayerId, name: &str) {
+        let ctx = sp.resolve::<LayerCtx>().unwrap();
+
+        ctx.change(LayerCtxInner {
+            id,
+            name: name.to_string(),
+        });
+    }
+
     pub fn ty(&self) -> TypeInfo {
         self.ty
     }
@@ -200,12 +1211,68 @@ impl Layer {
     pub fn id(&self) -> LayerId {
         self.id
     }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Layer service built, `LayerState::Created`
+    pub fn is_created(&self) -> bool {
+        matches!(self.state, LayerState::Created { .. })
+    }
+
+    /// Layer shut down by `LayersStack::shutdown`, skipped by updates
+    pub fn is_shutdown(&self) -> bool {
+        matches!(self.state, LayerState::Shutdown)
+    }
+
+    /// Layer and layer group enabled
+    pub fn active(&self) -> bool {
+        self.enabled && self.group_enabled
+    }
+
+    pub fn group(&self) -> Option<&str> {
+        self.group.as_deref()
+    }
+
+    pub fn stage(&self) -> Stage {
+        self.stage
+    }
+
+    pub fn errors(&self) -> &VecDeque<LayerError> {
+        &self.errors
+    }
+
+    /// Interpolation factor for fixed rate layer
+    pub fn interpolation_alpha(&self) -> Option<f64> {
+        self.fixed_timestep.as_ref().map(|fixed_timestep| fixed_timestep.alpha())
+    }
 }
 
 #[derive(Debug)]
 pub enum LayerState {
     Pending,
     Created { service: Box<dyn ILayer> },
+    /// Terminal state after stack shutdown
+    Shutdown,
+}
+
+pub struct LayerErrorCallback(Box<dyn Fn(&LayerError)>);
+
+impl Debug for LayerErrorCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("LayerErrorCallback").finish()
+    }
+}
+
+impl LayerErrorCallback {
+    fn call(&self, err: &LayerError) {
+        (self.0)(err)
+    }
 }
 
 pub struct LayerBuilder(Box<dyn Fn(ServiceProvider) -> anyhow::Result<Box<dyn ILayer>>>);
@@ -227,13 +1294,27 @@ pub struct LayerCtx {
     inner: Arc<RwLock<LayerCtxInner>>,
 }
 
+tokio::task_local! {
+    static LAYER_TASK_CTX: LayerTaskCtx;
+}
+
+/// Layer identity, in scheduled task resolved from task context instead of thread
 impl LayerCtx {
     pub fn id(&self) -> LayerId {
-        self.inner.read().id
+        LAYER_TASK_CTX
+            .try_with(|ctx| ctx.id)
+            .unwrap_or_else(|_| self.inner.read().id)
     }
 
     pub fn name(&self) -> String {
-        self.inner.read().name.clone()
+        LAYER_TASK_CTX
+            .try_with(|ctx| ctx.name.clone())
+            .unwrap_or_else(|_| self.inner.read().name.clone())
+    }
+
+    /// Context of current scheduled task, `None` outside of scheduler tasks
+    pub fn task() -> Option<LayerTaskCtx> {
+        LAYER_TASK_CTX.try_with(Clone::clone).ok()
     }
 
     pub(crate) fn change(&self, ctx: LayerCtxInner) {
@@ -241,6 +1322,24 @@ impl LayerCtx {
     }
 }
 
+/// Layer which scheduled task, available inside task by `LayerCtx::task`
+#[derive(Debug, Clone)]
+pub struct LayerTaskCtx {
+    pub id: LayerId,
+    pub name: String,
+    /// Scheduler frame number
+    pub frame: u64,
+}
+
+impl LayerTaskCtx {
+    /// Run future with task context and layer tracing span
+    pub(crate) fn scope<TFut: Future>(self, fut: TFut) -> impl Future<Output = TFut::Output> {
+        let span = tracing::info_span!("layer", name = %self.name, id = %self.id, frame = self.frame);
+
+        LAYER_TASK_CTX.scope(self, fut.instrument(span))
+    }
+}
+
 #[derive(Debug)]
 pub struct LayerCtxInner {
     id: LayerId,
@@ -1,9 +1,28 @@
+use events::EventsRegistry;
 use layer::{LayerCtx, LayersStack};
+use profiler::FrameProfiler;
 use scheduler::LayerScheduler;
+use stats::FrameStats;
+use time::{Clock, FixedTimeState};
 use xdi::builder::DiBuilder;
 
+pub mod background;
+pub mod condition;
+pub mod config;
+pub mod events;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod inspector;
 pub mod layer;
+pub mod pipeline;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod plugin;
+pub mod profiler;
 pub mod scheduler;
+pub mod stage;
+pub mod stats;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod time;
 pub mod types;
 
 #[cfg(test)]
@@ -16,6 +35,11 @@ pub trait ILayersSystemDependencies {
 impl ILayersSystemDependencies for DiBuilder {
     fn register_layers_system_dependencies(&self) {
         self.thread_local(|_| Ok(LayerCtx::default()));
+        self.singletone(Clock::new);
+        self.singletone(FixedTimeState::new);
+        self.singletone(FrameProfiler::new);
+        self.singletone(FrameStats::new);
+        self.singletone(EventsRegistry::new);
         self.transient(LayerScheduler::new);
         self.transient(LayersStack::new);
     }
@@ -0,0 +1,95 @@
+use std::sync::Arc;
+
+use parking_lot::RwLock;
+use xdi::{ServiceProvider, builder::DiBuilder, types::error::ServiceBuildResult};
+
+use crate::stats::FrameStats;
+
+pub trait ILayersPipelineDependencies {
+    /// Register `Snapshot<T>` singleton
+    fn register_snapshot<T: Send + Sync + 'static>(&self);
+}
+
+impl ILayersPipelineDependencies for DiBuilder {
+    fn register_snapshot<T: Send + Sync + 'static>(&self) {
+        self.singletone(Snapshot::<T>::new);
+    }
+}
+
+/// Data extracted by update stage layers for render stage layers.
+/// Render layer takes snapshot in `on_update` and moves it into tasks, so tasks of pipelined frame
+/// keep own frame data while next frame extracts new snapshot
+pub struct Snapshot<T> {
+    latest: Arc<RwLock<Option<Extracted<T>>>>,
+    stats: FrameStats,
+}
+
+/// Snapshot value with root frame index of extraction
+pub struct Extracted<T> {
+    pub frame: u64,
+    pub value: Arc<T>,
+}
+
+impl<T> Clone for Extracted<T> {
+    fn clone(&self) -> Self {
+        Self {
+            frame: self.frame,
+            value: self.value.clone(),
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for Extracted<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Extracted")
+            .field("type", &std::any::type_name::<T>())
+            .field("frame", &self.frame)
+            .finish()
+    }
+}
+
+impl<T> Clone for Snapshot<T> {
+    fn clone(&self) -> Self {
+        Self {
+            latest: self.latest.clone(),
+            stats: self.stats.clone(),
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for Snapshot<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Snapshot")
+            .field("type", &std::any::type_name::<T>())
+            .field("frame", &self.frame())
+            .finish()
+    }
+}
+
+impl<T: Send + Sync + 'static> Snapshot<T> {
+    pub fn new(sp: ServiceProvider) -> ServiceBuildResult<Self> {
+        Ok(Self {
+            latest: Default::default(),
+            stats: sp.resolve()?,
+        })
+    }
+}
+
+impl<T> Snapshot<T> {
+    /// Replace latest snapshot, already taken snapshots not changed
+    pub fn extract(&self, value: T) {
+        *self.latest.write() = Some(Extracted {
+            frame: self.stats.frame(),
+            value: Arc::new(value),
+        });
+    }
+
+    pub fn latest(&self) -> Option<Extracted<T>> {
+        self.latest.read().clone()
+    }
+
+    /// Frame of latest snapshot, older than current frame if extracting layer skipped update
+    pub fn frame(&self) -> Option<u64> {
+        self.latest.read().as_ref().map(|extracted| extracted.frame)
+    }
+}
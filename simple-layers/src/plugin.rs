@@ -0,0 +1,535 @@
+use std::{
+    collections::HashMap,
+    mem::ManuallyDrop,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use libloading::Library;
+use xdi::{ServiceProvider, builder::DiBuilder};
+
+use crate::{
+    layer::{ILayer, Layer, LayerPosition, LayersStack},
+    types::error::PluginError,
+};
+
+/// Bumped on any `PluginRegistrar` change, plugin with other version rejected
+pub const PLUGIN_ABI_VERSION: u32 = 2;
+
+pub const PLUGIN_ENTRY_SYMBOL: &str = "simple_layers_plugin_entry";
+
+/// Library entry point, called with engine abi version and return plugin abi version.
+/// Plugin register layers only if versions match, registrar layout is shared only by
+/// plugin and engine built by the same toolchain
+pub type PluginEntry = unsafe extern "C" fn(abi_version: u32, registrar: *mut PluginRegistrar) -> u32;
+
+/// Registration function of plugin linked into binary
+pub type PluginRegister = fn(&mut PluginRegistrar);
+
+/// Export plugin entry point from `cdylib` crate.
+/// Services can't be hot-reloaded, reload of plugin registering services fails
+#[macro_export]
+macro_rules! export_plugin {
+    ($register:path) => {
+        /// # Safety
+        /// `registrar` should be valid registrar pointer, used only if abi versions match
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C" fn simple_layers_plugin_entry(
+            abi_version: u32,
+            registrar: *mut $crate::plugin::PluginRegistrar,
+        ) -> u32 {
+            if abi_version == $crate::plugin::PLUGIN_ABI_VERSION {
+                $register(unsafe { &mut *registrar });
+            }
+
+            $crate::plugin::PLUGIN_ABI_VERSION
+        }
+    };
+}
+
+/// Layers and services registered by plugin entry point
+#[derive(Debug, Default)]
+pub struct PluginRegistrar {
+    layers: Vec<PluginLayer>,
+    services: Vec<ServicesRegistration>,
+}
+
+#[derive(Debug)]
+struct PluginLayer {
+    position: PluginLayerPosition,
+    group: Option<String>,
+    layer: Layer,
+}
+
+#[derive(Debug, Clone)]
+enum PluginLayerPosition {
+    Last,
+    Before(String),
+    After(String),
+}
+
+impl PluginLayerPosition {
+    fn as_position(&self) -> LayerPosition<'_> {
+        match self {
+            PluginLayerPosition::Last => LayerPosition::Last,
+            PluginLayerPosition::Before(layer) => LayerPosition::Before(layer.into()),
+            PluginLayerPosition::After(layer) => LayerPosition::After(layer.into()),
+        }
+    }
+}
+
+struct ServicesRegistration(Box<dyn FnOnce(&DiBuilder)>);
+
+impl std::fmt::Debug for ServicesRegistration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ServicesRegistration").finish()
+    }
+}
+
+impl PluginRegistrar {
+    pub fn push_layer<
+        TLayer: ILayer + 'static,
+        TCtr: Fn(ServiceProvider) -> anyhow::Result<TLayer> + 'static,
+    >(
+        &mut self,
+        name: impl Into<String>,
+        layer_ctr: TCtr,
+    ) -> &mut Layer {
+        self.add_layer(PluginLayerPosition::Last, name.into(), layer_ctr)
+    }
+
+    pub fn insert_before<
+        TLayer: ILayer + 'static,
+        TCtr: Fn(ServiceProvider) -> anyhow::Result<TLayer> + 'static,
+    >(
+        &mut self,
+        target: impl Into<String>,
+        name: impl Into<String>,
+        layer_ctr: TCtr,
+    ) -> &mut Layer {
+        self.add_layer(PluginLayerPosition::Before(target.into()), name.into(), layer_ctr)
+    }
+
+    pub fn insert_after<
+        TLayer: ILayer + 'static,
+        TCtr: Fn(ServiceProvider) -> anyhow::Result<TLayer> + 'static,
+    >(
+        &mut self,
+        target: impl Into<String>,
+        name: impl Into<String>,
+        layer_ctr: TCtr,
+    ) -> &mut Layer {
+        self.add_layer(PluginLayerPosition::After(target.into()), name.into(), layer_ctr)
+    }
+
+    /// Put last registered layer into group
+    pub fn with_group(&mut self, group: impl Into<String>) -> &mut Self {
+        if let Some(layer) = self.layers.last_mut() {
+            layer.group = Some(group.into());
+        }
+
+        self
+    }
+
+    /// Services registered only if plugin loaded before `DiBuilder::build`.
+    /// Reload fails with `PluginError::ServicesOnReload` if new library registers services
+    pub fn register_services(&mut self, register: impl FnOnce(&DiBuilder) + 'static) {
+        self.services.push(ServicesRegistration(Box::new(register)));
+    }
+
+    fn add_layer<
+        TLayer: ILayer + 'static,
+        TCtr: Fn(ServiceProvider) -> anyhow::Result<TLayer> + 'static,
+    >(
+        &mut self,
+        position: PluginLayerPosition,
+        name: String,
+        layer_ctr: TCtr,
+    ) -> &mut Layer {
+        self.layers.push(PluginLayer {
+            position,
+            group: None,
+            layer: Layer::new(name, layer_ctr, true),
+        });
+
+        &mut self.layers.last_mut().unwrap().layer
+    }
+}
+
+/// Loads layers plugins and reloads changed plugin files between frames.
+/// Libraries of plugins with registered services stay loaded until loader dropped,
+/// so loader should be dropped after services provider
+#[derive(Debug, Default)]
+pub struct PluginLoader {
+    plugins: Vec<Plugin>,
+    /// Unloaded libraries, services instances built by library code may still be alive
+    retained: Vec<LoadedLibrary>,
+}
+
+#[derive(Debug)]
+struct Plugin {
+    name: String,
+    source: PluginSource,
+    registrar: PluginRegistrar,
+    /// Layers attached to stack, in stack order
+    layers: Vec<String>,
+    /// Library registered services, instances may outlive plugin layers
+    keep_loaded: bool,
+}
+
+#[derive(Debug)]
+enum PluginSource {
+    Static(PluginRegister),
+    Library {
+        path: PathBuf,
+        modified: Option<SystemTime>,
+        generation: u32,
+        loaded: Option<LoadedLibrary>,
+    },
+}
+
+#[derive(Debug)]
+struct LoadedLibrary {
+    /// Closed before copy removed
+    library: ManuallyDrop<Library>,
+    /// Library loaded from copy, original file may be rewritten by build
+    copy_path: PathBuf,
+}
+
+impl PluginLoader {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Load plugin from shared library, plugin name is file stem
+    pub fn load(&mut self, path: impl AsRef<Path>) -> Result<&str, PluginError> {
+        let path = path.as_ref().to_path_buf();
+
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.display().to_string());
+
+        let mut source = PluginSource::Library {
+            path,
+            modified: None,
+            generation: 0,
+            loaded: None,
+        };
+
+        let registrar = source.register()?;
+
+        Ok(self.add_plugin(name, source, registrar))
+    }
+
+    /// Register plugin linked into binary, reloaded only by `reload`
+    pub fn load_static(&mut self, name: impl Into<String>, register: PluginRegister) -> Result<&str, PluginError> {
+        let mut source = PluginSource::Static(register);
+
+        let registrar = source.register()?;
+
+        Ok(self.add_plugin(name.into(), source, registrar))
+    }
+
+    fn add_plugin(&mut self, name: String, source: PluginSource, registrar: PluginRegistrar) -> &str {
+        tracing::info!("[{name}] Plugin loaded");
+
+        self.plugins.push(Plugin {
+            name,
+            source,
+            registrar,
+            layers: Vec::new(),
+            keep_loaded: false,
+        });
+
+        &self.plugins.last().unwrap().name
+    }
+
+    /// Register loaded plugins services, should be called before `DiBuilder::build`
+    pub fn register_services(&mut self, builder: &DiBuilder) {
+        for plugin in &mut self.plugins {
+            if plugin.registrar.services.is_empty() {
+                continue;
+            }
+
+            plugin.keep_loaded = true;
+
+            for services in plugin.registrar.services.drain(..) {
+                (services.0)(builder);
+            }
+        }
+    }
+
+    /// Attach layers of loaded plugins to stack
+    pub fn attach(&mut self, stack: &mut LayersStack) -> Result<(), PluginError> {
+        for plugin in &mut self.plugins {
+            plugin.attach(stack, &HashMap::default())?;
+        }
+
+        Ok(())
+    }
+
+    /// Reload plugins with changed files, should be called between frames.
+    /// Every changed plugin reloaded, failed plugins keep old layers and reported together
+    pub fn poll_reload(&mut self, stack: &mut LayersStack) -> Result<bool, PluginError> {
+        let mut reloaded = false;
+        let mut errors = Vec::new();
+
+        for plugin in &mut self.plugins {
+            if !plugin.source.changed() {
+                continue;
+            }
+
+            match plugin.reload(stack, &mut self.retained) {
+                Ok(()) => reloaded = true,
+                Err(err) => {
+                    tracing::error!("[{name}] Plugin reload failed: {err}", name = plugin.name);
+                    errors.push(err);
+                }
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(PluginError::Reload { errors });
+        }
+
+        Ok(reloaded)
+    }
+
+    /// Force plugin reload, `on_detach` called for old layers and `on_attach` for new on next update.
+    /// Old layers keep running if new library failed to load
+    pub fn reload(&mut self, name: &str, stack: &mut LayersStack) -> Result<(), PluginError> {
+        let Some(plugin) = self.plugins.iter_mut().find(|plugin| plugin.name == name) else {
+            return Err(PluginError::NotFound { plugin: name.to_string() });
+        };
+
+        plugin.reload(stack, &mut self.retained)
+    }
+
+    /// Remove plugin layers and unload plugin
+    pub fn unload(&mut self, name: &str, stack: &mut LayersStack) -> Result<(), PluginError> {
+        let Some(index) = self.plugins.iter().position(|plugin| plugin.name == name) else {
+            return Err(PluginError::NotFound { plugin: name.to_string() });
+        };
+
+        let mut plugin = self.plugins.remove(index);
+
+        plugin.detach(stack);
+        self.retained.extend(plugin.source.replace(None, plugin.keep_loaded));
+
+        Ok(())
+    }
+
+    /// Loaded plugins names
+    pub fn plugins(&self) -> impl Iterator<Item = &str> {
+        self.plugins.iter().map(|plugin| plugin.name.as_str())
+    }
+}
+
+impl Plugin {
+    /// Attach registered layers, layers from previous load placed at previous positions
+    fn attach(
+        &mut self,
+        stack: &mut LayersStack,
+        anchors: &HashMap<String, PluginLayerPosition>,
+    ) -> Result<(), PluginError> {
+        for plugin_layer in self.registrar.layers.drain(..) {
+            let name = plugin_layer.layer.name().to_string();
+
+            let position = anchors.get(&name).unwrap_or(&plugin_layer.position);
+
+            stack.attach_plugin_layer(position.as_position(), plugin_layer.group, plugin_layer.layer)?;
+
+            self.layers.push(name);
+        }
+
+        Ok(())
+    }
+
+    /// Remove plugin layers, return next non plugin layer for every removed layer
+    fn detach(&mut self, stack: &mut LayersStack) -> HashMap<String, PluginLayerPosition> {
+        let order = stack.layers().map(|layer| layer.name().to_string()).collect::<Vec<_>>();
+
+        let mut anchors = HashMap::new();
+
+        let plugin_layers = std::mem::take(&mut self.layers);
+
+        for layer in &plugin_layers {
+            let Some(index) = order.iter().position(|name| name == layer) else {
+                continue;
+            };
+
+            let anchor = order[index + 1..]
+                .iter()
+                .find(|name| !plugin_layers.contains(name))
+                .map(|name| PluginLayerPosition::Before(name.clone()))
+                .unwrap_or(PluginLayerPosition::Last);
+
+            if let Err(err) = stack.remove_layer(layer) {
+                tracing::warn!("[{name}] Plugin layer remove failed: {err}", name = self.name);
+            }
+
+            anchors.insert(layer.clone(), anchor);
+        }
+
+        // Removed layers tasks and background services should be completed before plugin code unloaded
+        if let Err(err) = stack.scheduler_mut().wait_unload_blocking() {
+            tracing::warn!("[{name}] {err}", name = self.name);
+        }
+
+        anchors
+    }
+
+    fn reload(&mut self, stack: &mut LayersStack, retained: &mut Vec<LoadedLibrary>) -> Result<(), PluginError> {
+        tracing::info!("[{name}] Plugin reload", name = self.name);
+
+        // Old layers detached only after new library validated
+        let (registrar, library) = self.source.load()?;
+
+        if !registrar.services.is_empty() {
+            // Registrations reference new library code
+            drop(registrar);
+            drop(library);
+
+            return Err(PluginError::ServicesOnReload {
+                plugin: self.name.clone(),
+            });
+        }
+
+        let anchors = self.detach(stack);
+
+        // Old layers constructors reference old library code
+        drop(std::mem::replace(&mut self.registrar, registrar));
+        retained.extend(self.source.replace(library, self.keep_loaded));
+
+        // Services registered only by first loaded library
+        self.keep_loaded = false;
+
+        self.attach(stack, &anchors)
+    }
+}
+
+impl PluginSource {
+    fn register(&mut self) -> Result<PluginRegistrar, PluginError> {
+        let (registrar, library) = self.load()?;
+
+        self.replace(library, false);
+
+        Ok(registrar)
+    }
+
+    /// Load and register new library, current library stays loaded until `replace`
+    fn load(&mut self) -> Result<(PluginRegistrar, Option<LoadedLibrary>), PluginError> {
+        match self {
+            PluginSource::Static(register) => {
+                let mut registrar = PluginRegistrar::default();
+
+                register(&mut registrar);
+
+                Ok((registrar, None))
+            }
+            PluginSource::Library {
+                path,
+                modified,
+                generation,
+                ..
+            } => {
+                // Failed file not loaded again until changed
+                *modified = file_modified(path);
+                *generation += 1;
+
+                let library = LoadedLibrary::load(path, *generation)?;
+
+                // Dropped before library on error
+                let mut registrar = PluginRegistrar::default();
+
+                library.register(path, &mut registrar)?;
+
+                Ok((registrar, Some(library)))
+            }
+        }
+    }
+
+    fn changed(&self) -> bool {
+        match self {
+            PluginSource::Static(_) => false,
+            PluginSource::Library { path, modified, .. } => {
+                let current = file_modified(path);
+                current.is_some() && current != *modified
+            }
+        }
+    }
+
+    /// Replace current library and close it, library returned if it should be retained by loader
+    fn replace(&mut self, library: Option<LoadedLibrary>, keep_loaded: bool) -> Option<LoadedLibrary> {
+        let PluginSource::Library { loaded, .. } = self else {
+            return None;
+        };
+
+        std::mem::replace(loaded, library).filter(|_| keep_loaded)
+    }
+}
+
+impl LoadedLibrary {
+    fn load(path: &Path, generation: u32) -> Result<Self, PluginError> {
+        let load_err = |message: String| PluginError::Load {
+            path: path.display().to_string(),
+            message,
+        };
+
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| load_err("Invalid plugin path".to_string()))?
+            .to_string_lossy();
+
+        let copy_path = std::env::temp_dir().join(format!("{}-{generation}-{file_name}", std::process::id()));
+
+        std::fs::copy(path, &copy_path).map_err(|err| load_err(err.to_string()))?;
+
+        // SAFETY: plugin library initializers trusted same as engine code
+        let library = unsafe { Library::new(&copy_path) }.map_err(|err| {
+            _ = std::fs::remove_file(&copy_path);
+            load_err(err.to_string())
+        })?;
+
+        Ok(Self {
+            library: ManuallyDrop::new(library),
+            copy_path,
+        })
+    }
+
+    fn register(&self, path: &Path, registrar: &mut PluginRegistrar) -> Result<(), PluginError> {
+        // SAFETY: entry type defined by `export_plugin` macro
+        let entry = unsafe { self.library.get::<PluginEntry>(PLUGIN_ENTRY_SYMBOL.as_bytes()) }.map_err(|_| {
+            PluginError::MissingEntry {
+                path: path.display().to_string(),
+            }
+        })?;
+
+        // SAFETY: plugin use registrar only if abi versions match
+        let found = unsafe { entry(PLUGIN_ABI_VERSION, registrar) };
+
+        if found != PLUGIN_ABI_VERSION {
+            return Err(PluginError::AbiMismatch {
+                path: path.display().to_string(),
+                expected: PLUGIN_ABI_VERSION,
+                found,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for LoadedLibrary {
+    fn drop(&mut self) {
+        // SAFETY: library not used after drop, plugin layers and tasks completed before unload
+        unsafe { ManuallyDrop::drop(&mut self.library) };
+
+        _ = std::fs::remove_file(&self.copy_path);
+    }
+}
+
+fn file_modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
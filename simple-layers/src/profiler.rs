@@ -0,0 +1,305 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+use serde_json::{Value, json};
+use xdi::{ServiceProvider, types::error::ServiceBuildResult};
+
+use crate::types::id::LayerId;
+
+const DEFAULT_HISTORY_LEN: usize = 120;
+
+/// Per frame layers and tasks timings, disabled by default.
+/// All times are offsets from profiler creation
+#[derive(Debug, Clone)]
+pub struct FrameProfiler {
+    enabled: Arc<AtomicBool>,
+    inner: Arc<Mutex<FrameProfilerInner>>,
+}
+
+#[derive(Debug)]
+struct FrameProfilerInner {
+    epoch: Instant,
+    history_len: usize,
+    frame_index: u64,
+    current: Option<FrameProfile>,
+    history: VecDeque<FrameProfile>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FrameProfile {
+    pub index: u64,
+    pub start: Duration,
+    pub end: Duration,
+    pub layers: Vec<LayerSpan>,
+    pub tasks: Vec<TaskSpan>,
+    /// `LayerScheduler::wait_all_blocking` calls
+    pub waits: Vec<Span>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Span {
+    pub start: Duration,
+    pub end: Duration,
+}
+
+impl Span {
+    pub fn duration(&self) -> Duration {
+        self.end.saturating_sub(self.start)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LayerSpan {
+    pub layer_id: LayerId,
+    pub layer_name: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct TaskSpan {
+    pub layer_id: LayerId,
+    pub layer_name: String,
+    pub queued: Duration,
+    /// Task run, after dependencies completed
+    pub span: Span,
+}
+
+impl FrameProfiler {
+    pub fn new(_: ServiceProvider) -> ServiceBuildResult<Self> {
+        Ok(Self {
+            enabled: Default::default(),
+            inner: Arc::new(Mutex::new(FrameProfilerInner {
+                epoch: Instant::now(),
+                history_len: DEFAULT_HISTORY_LEN,
+                frame_index: 0,
+                current: None,
+                history: Default::default(),
+            })),
+        })
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Max frames in rolling history
+    pub fn set_history_len(&self, history_len: usize) {
+        let mut inner = self.inner.lock();
+
+        inner.history_len = history_len;
+
+        while inner.history.len() > history_len {
+            inner.history.pop_front();
+        }
+    }
+
+    pub fn history(&self) -> Vec<FrameProfile> {
+        self.inner.lock().history.iter().cloned().collect()
+    }
+
+    pub fn last_frame(&self) -> Option<FrameProfile> {
+        self.inner.lock().history.back().cloned()
+    }
+
+    pub fn clear(&self) {
+        self.inner.lock().history.clear();
+    }
+
+    /// Offset from profiler creation
+    pub fn now(&self) -> Duration {
+        self.inner.lock().epoch.elapsed()
+    }
+
+    pub(crate) fn offset(&self, time: Instant) -> Duration {
+        time.saturating_duration_since(self.inner.lock().epoch)
+    }
+
+    /// Current frame index, if profiler enabled and frame started
+    pub(crate) fn current_frame(&self) -> Option<u64> {
+        if !self.enabled() {
+            return None;
+        }
+
+        self.inner.lock().current.as_ref().map(|frame| frame.index)
+    }
+
+    /// Return false if profiler disabled or frame already started by parent stack
+    pub(crate) fn begin_frame(&self) -> bool {
+        if !self.enabled() {
+            return false;
+        }
+
+        let mut inner = self.inner.lock();
+
+        if inner.current.is_some() {
+            return false;
+        }
+
+        inner.frame_index += 1;
+
+        let start = inner.epoch.elapsed();
+
+        inner.current = Some(FrameProfile {
+            index: inner.frame_index,
+            start,
+            end: start,
+            layers: Vec::new(),
+            tasks: Vec::new(),
+            waits: Vec::new(),
+        });
+
+        true
+    }
+
+    pub(crate) fn end_frame(&self) {
+        let mut inner = self.inner.lock();
+
+        let Some(mut frame) = inner.current.take() else {
+            return;
+        };
+
+        frame.end = inner.epoch.elapsed();
+
+        if inner.history.len() >= inner.history_len {
+            inner.history.pop_front();
+        }
+
+        if inner.history_len > 0 {
+            inner.history.push_back(frame);
+        }
+    }
+
+    pub(crate) fn record_layer(&self, layer_id: LayerId, layer_name: &str, start: Instant, end: Instant) {
+        let mut inner = self.inner.lock();
+        let span = inner.span(start, end);
+
+        if let Some(frame) = &mut inner.current {
+            frame.layers.push(LayerSpan {
+                layer_id,
+                layer_name: layer_name.to_string(),
+                span,
+            });
+        }
+    }
+
+    pub(crate) fn record_wait(&self, start: Instant, end: Instant) {
+        let mut inner = self.inner.lock();
+        let span = inner.span(start, end);
+
+        if let Some(frame) = &mut inner.current {
+            frame.waits.push(span);
+        }
+    }
+
+    /// Task may outlive own frame, in that case task recorded into history frame
+    pub(crate) fn record_task(&self, frame_index: u64, task: TaskSpan) {
+        let inner = &mut *self.inner.lock();
+
+        let frame = inner
+            .current
+            .iter_mut()
+            .chain(inner.history.iter_mut().rev())
+            .find(|frame| frame.index == frame_index);
+
+        if let Some(frame) = frame {
+            frame.tasks.push(task);
+        }
+    }
+
+    /// Export history in Chrome `trace_event` JSON format
+    pub fn to_chrome_trace(&self) -> String {
+        chrome_trace(&self.history()).to_string()
+    }
+}
+
+impl FrameProfilerInner {
+    fn span(&self, start: Instant, end: Instant) -> Span {
+        Span {
+            start: start.saturating_duration_since(self.epoch),
+            end: end.saturating_duration_since(self.epoch),
+        }
+    }
+}
+
+const STACK_TID: usize = 0;
+
+/// Frames, layers updates and waits on stack thread, tasks of every layer on own track
+pub fn chrome_trace(frames: &[FrameProfile]) -> Value {
+    let mut events = Vec::new();
+    let mut tasks_tracks = Vec::<&str>::new();
+
+    events.push(thread_name_event(STACK_TID, "layers stack"));
+
+    for frame in frames {
+        let span = Span {
+            start: frame.start,
+            end: frame.end,
+        };
+
+        events.push(complete_event(&format!("frame {}", frame.index), "frame", STACK_TID, span, None));
+
+        for layer in &frame.layers {
+            events.push(complete_event(&layer.layer_name, "layer", STACK_TID, layer.span, None));
+        }
+
+        for wait in &frame.waits {
+            events.push(complete_event("wait_all_blocking", "wait", STACK_TID, *wait, None));
+        }
+
+        for task in &frame.tasks {
+            let track = match tasks_tracks.iter().position(|name| *name == task.layer_name) {
+                Some(track) => track,
+                None => {
+                    tasks_tracks.push(&task.layer_name);
+                    events.push(thread_name_event(tasks_tracks.len(), &format!("{} tasks", task.layer_name)));
+                    tasks_tracks.len() - 1
+                }
+            };
+
+            let queued = task.span.start.saturating_sub(task.queued);
+
+            events.push(complete_event(&task.layer_name, "task", track + 1, task.span, Some(queued)));
+        }
+    }
+
+    json!({ "traceEvents": events })
+}
+
+fn complete_event(name: &str, category: &str, tid: usize, span: Span, queued: Option<Duration>) -> Value {
+    let mut event = json!({
+        "name": name,
+        "cat": category,
+        "ph": "X",
+        "pid": 1,
+        "tid": tid,
+        "ts": span.start.as_micros() as u64,
+        "dur": span.duration().as_micros() as u64,
+    });
+
+    if let Some(queued) = queued {
+        event["args"] = json!({ "queued_us": queued.as_micros() as u64 });
+    }
+
+    event
+}
+
+fn thread_name_event(tid: usize, name: &str) -> Value {
+    json!({
+        "name": "thread_name",
+        "ph": "M",
+        "pid": 1,
+        "tid": tid,
+        "args": { "name": name },
+    })
+}
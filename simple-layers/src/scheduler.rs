@@ -1,13 +1,28 @@
-use std::collections::HashMap;
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BinaryHeap, HashMap, VecDeque},
+    future::poll_fn,
+    pin::{Pin, pin},
+    sync::Arc,
+    task::Poll,
+    time::{Duration, Instant},
+};
 
-use tokio::runtime::Handle;
+use parking_lot::Mutex;
+use tokio::{runtime::Handle, sync::oneshot, task::JoinHandle};
 use xdi::{IAsyncTaskScope, ServiceProvider, types::error::ServiceBuildResult};
 
 use crate::{
-    layer::LayerCtx,
+    background::{BackgroundOptions, BackgroundServiceInfo, BackgroundServices},
+    layer::{Layer, LayerCtx, LayerTaskCtx},
+    profiler::{FrameProfiler, Span, TaskSpan},
+    stage::Stage,
     types::{
+        error::{ScheduleDiagnostic, ScheduleError, TaskError, TaskErrorKind},
         id::LayerId,
-        sync::{Waiter, signal_channel},
+        sync::{CancellationToken, SignalPool, Waiter},
+        task::{CatchUnwind, ResourceAccess, StragglerReport, StragglerTask, TaskHandle, TaskOptions, panic_message},
+        type_info::TypeInfo,
     },
 };
 
@@ -15,99 +30,1174 @@ use crate::{
 pub struct LayerScheduler {
     handler: Handle,
     sp: ServiceProvider,
-    scheduled_tasks: HashMap<LayerId, Vec<Waiter>, ahash::RandomState>,
-    scheduled_tasks_by_name: HashMap<String, LayerId>,
+    scheduled_tasks: HashMap<LayerId, Vec<ScheduledTask>, ahash::RandomState>,
+
+    /// Tasks of current frame graph, graph resolved on `wait_all_blocking`
+    pending_tasks: Vec<PendingTask>,
+    pending_by_layer: HashMap<LayerId, Vec<usize>, ahash::RandomState>,
+    /// Edges of first pending tasks, resolved on schedule
+    resolved_edges: Vec<Vec<usize>>,
+    /// Diagnostics of tasks resolved on schedule
+    resolved_diagnostics: Vec<ScheduleDiagnostic>,
+    /// Task with forward, unknown or optional dependency scheduled, next tasks resolved on wait
+    graph_open: bool,
+    known_layers: HashMap<String, KnownLayer, ahash::RandomState>,
+
+    strict: bool,
+    diagnostics: Vec<ScheduleDiagnostic>,
+
+    /// Panicked and timed out tasks, reported to owning layers by `LayersStack::update`
+    task_errors: Arc<Mutex<Vec<TaskError>>>,
+
+    layer_cancellations: HashMap<LayerId, CancellationToken, ahash::RandomState>,
+
+    frame_deadline: Option<Duration>,
+    frame_start: Option<Instant>,
+    straggler_report: Option<StragglerReport>,
+
+    profiler: FrameProfiler,
+
+    background: BackgroundServices,
+
+    /// Tasks run by `wait_all_blocking` on calling thread
+    main_thread_tasks: Vec<MainThreadTask>,
+
+    signals: SignalPool,
+
+    /// Max frames with not completed render stage tasks, 0 disables pipelining
+    pipeline_depth: usize,
+    /// Render stage tasks of previous frames by layer, oldest first
+    in_flight: VecDeque<Vec<(LayerId, Vec<ScheduledTask>)>>,
+    /// Waiters of last deferred frame, next frame render tasks run after them
+    pipeline_tail: Vec<Waiter>,
+
+    frame: u64,
 }
 
 impl LayerScheduler {
     pub fn new(sp: ServiceProvider) -> ServiceBuildResult<Self> {
         let handler = sp.resolve::<Handle>()?;
+        let profiler = sp.resolve::<FrameProfiler>()?;
 
         Ok(Self {
             handler,
             sp,
             scheduled_tasks: Default::default(),
-            scheduled_tasks_by_name: Default::default(),
+            pending_tasks: Default::default(),
+            pending_by_layer: Default::default(),
+            resolved_edges: Vec::new(),
+            resolved_diagnostics: Vec::new(),
+            graph_open: false,
+            known_layers: Default::default(),
+            strict: false,
+            diagnostics: Default::default(),
+            task_errors: Default::default(),
+            layer_cancellations: Default::default(),
+            frame_deadline: None,
+            frame_start: None,
+            straggler_report: None,
+            profiler,
+            background: Default::default(),
+            main_thread_tasks: Vec::new(),
+            signals: Default::default(),
+            pipeline_depth: 0,
+            in_flight: Default::default(),
+            pipeline_tail: Vec::new(),
+            frame: 0,
         })
     }
 
-    /// Schedule a task to be performed after another layer's tasks are completed
+    /// In strict mode `wait_all_blocking` return error on any dependency diagnostic
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// Frame number, first frame is 1
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Dependency diagnostics collected in current frame
+    pub fn diagnostics(&self) -> &[ScheduleDiagnostic] {
+        &self.diagnostics
+    }
+
+    /// Max time from frame start to wait tasks, not completed tasks cancelled and reported after deadline.
+    /// Main thread tasks included. Deadline require tokio runtime with enabled time driver
+    pub fn set_frame_deadline(&mut self, deadline: Option<Duration>) {
+        self.frame_deadline = deadline;
+    }
+
+    /// Let `Stage::Render` layers tasks of up to `depth` frames run while next frames updated.
+    /// Render tasks of every frame run after render tasks of previous frame.
+    /// Frame waiting oldest frame in flight applies own frame deadline to its render tasks
+    pub fn set_pipeline_depth(&mut self, depth: usize) {
+        self.pipeline_depth = depth;
+    }
+
+    pub fn pipeline_depth(&self) -> usize {
+        self.pipeline_depth
+    }
+
+    /// Frames with deferred render tasks
+    pub fn frames_in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    /// Tasks not completed before deadline in current frame
+    pub fn straggler_report(&self) -> Option<&StragglerReport> {
+        self.straggler_report.as_ref()
+    }
+
+    /// Cancellation token of current layer tasks
+    pub fn cancellation_token(&mut self) -> CancellationToken {
+        let lc = self.sp.resolve::<LayerCtx>().unwrap();
+
+        self.layer_cancellations.entry(lc.id()).or_default().clone()
+    }
+
+    /// Cancel all outstanding tasks of current layer, new tasks get new token
+    pub fn cancel_layer_tasks(&mut self) {
+        let lc = self.sp.resolve::<LayerCtx>().unwrap();
+
+        self.cancel_tasks_of(lc.id());
+    }
+
+    pub(crate) fn cancel_tasks_of(&mut self, layer_id: LayerId) {
+        if let Some(cancellation) = self.layer_cancellations.remove(&layer_id) {
+            cancellation.cancel();
+        }
+    }
+
+    /// Spawn background service of current layer, service lives across frames and talks to frame loop through channels.
+    /// Service stopped while layer disabled and cancelled on layer detach, service with same name replaced
+    pub fn spawn_background<TFut: Future<Output = anyhow::Result<()>> + Send + 'static>(
+        &mut self,
+        name: impl Into<String>,
+        factory: impl Fn(CancellationToken) -> TFut + Send + Sync + 'static,
+        options: BackgroundOptions,
+    ) {
+        let lc = self.sp.resolve::<LayerCtx>().unwrap();
+
+        let task_ctx = LayerTaskCtx {
+            id: lc.id(),
+            name: lc.name(),
+            frame: self.frame,
+        };
+
+        self.background
+            .spawn(&self.handler, task_ctx, name.into(), factory, options);
+    }
+
+    /// Background services of all layers, sorted by layer and service name
+    pub fn background_services(&self) -> Vec<BackgroundServiceInfo> {
+        self.background.infos()
+    }
+
+    pub(crate) fn stop_background_of(&mut self, layer_id: LayerId) {
+        self.background.stop_of(layer_id);
+    }
+
+    pub(crate) fn resume_background_of(&mut self, layer_id: LayerId) {
+        self.background.resume_of(&self.handler, layer_id);
+    }
+
+    pub(crate) fn remove_background_of(&mut self, layer_id: LayerId) {
+        self.background.remove_of(layer_id);
+    }
+
+    /// Refresh known layers, used to validate dependencies by name
+    pub(crate) fn begin_frame<'a>(&mut self, layers: impl Iterator<Item = &'a Layer>) {
+        self.diagnostics.clear();
+        self.known_layers.clear();
+        self.straggler_report = None;
+        self.frame_start = Some(Instant::now());
+        self.frame += 1;
+
+        self.signals.recycle();
+
+        for layer in layers {
+            self.known_layers.insert(
+                layer.name().to_string(),
+                KnownLayer {
+                    id: layer.id(),
+                    enabled: layer.active(),
+                    pipelined: self.pipeline_depth > 0 && layer.stage() == Stage::Render,
+                    updated: false,
+                },
+            );
+        }
+    }
+
+    /// Layer `on_update` completed, tasks of next layers may depend on its tasks without waiting frame graph
+    pub(crate) fn layer_updated(&mut self, name: &str) {
+        if let Some(layer) = self.known_layers.get_mut(name) {
+            layer.updated = true;
+        }
+    }
+
+    /// Layers update loop completed, tasks scheduled by hooks resolved on frame graph wait
+    pub(crate) fn end_layers_update(&mut self) {
+        for layer in self.known_layers.values_mut() {
+            layer.updated = false;
+        }
+    }
+
+    /// Schedule a task to be performed after another layer's tasks are completed.
+    /// Task depending only on already updated layers started immediately, forward dependencies wait frame graph
     pub fn schedule<'a, const DEPENDENCY_COUNT: usize>(
         &mut self,
         task: impl Future<Output = ()> + Send + Sync + 'static,
         deps: impl Into<Dependency<'a, DEPENDENCY_COUNT>>,
     ) -> Waiter {
+        self.schedule_with_result(task, deps).into_waiter()
+    }
+
+    /// Schedule a task same as `schedule`, task result or panic available through returned handle
+    pub fn schedule_with_result<'a, T: Send + 'static, const DEPENDENCY_COUNT: usize>(
+        &mut self,
+        task: impl Future<Output = T> + Send + Sync + 'static,
+        deps: impl Into<Dependency<'a, DEPENDENCY_COUNT>>,
+    ) -> TaskHandle<T> {
+        self.schedule_with_options(task, deps, TaskOptions::default())
+    }
+
+    /// Schedule a task same as `schedule_with_result` with timeout, extra cancellation or declared resources access
+    pub fn schedule_with_options<'a, T: Send + 'static, const DEPENDENCY_COUNT: usize>(
+        &mut self,
+        task: impl Future<Output = T> + Send + Sync + 'static,
+        deps: impl Into<Dependency<'a, DEPENDENCY_COUNT>>,
+        options: TaskOptions,
+    ) -> TaskHandle<T> {
+        let (layer_task, handle) = self.prepare_task(task, deps, options, false);
+
+        self.handler.spawn(layer_task);
+
+        handle
+    }
+
+    /// Run `f` for every item on workers, items split into chunks of `chunk_size` in items order.
+    /// Chunks run under single task of current layer, dependents wait all chunks
+    pub fn schedule_parallel<'a, T: Send + 'static, const DEPENDENCY_COUNT: usize>(
+        &mut self,
+        items: Vec<T>,
+        chunk_size: usize,
+        f: impl Fn(T) + Send + Sync + 'static,
+        deps: impl Into<Dependency<'a, DEPENDENCY_COUNT>>,
+    ) -> Waiter {
+        self.schedule_parallel_with_result(items, chunk_size, f, deps).into_waiter()
+    }
+
+    /// Schedule parallel work same as `schedule_parallel`, results collected in items order.
+    /// Panic of any chunk reported as panic of the whole task
+    pub fn schedule_parallel_with_result<'a, T: Send + 'static, R: Send + 'static, const DEPENDENCY_COUNT: usize>(
+        &mut self,
+        items: Vec<T>,
+        chunk_size: usize,
+        f: impl Fn(T) -> R + Send + Sync + 'static,
+        deps: impl Into<Dependency<'a, DEPENDENCY_COUNT>>,
+    ) -> TaskHandle<Vec<R>> {
+        assert!(chunk_size > 0, "Parallel chunk size should be positive");
+
+        let handler = self.handler.clone();
+        let f = Arc::new(f);
+
+        let chunks = split_chunks(items, chunk_size);
+
+        let task = async move {
+            let task_ctx = LayerCtx::task();
+
+            let mut chunk_tasks = ChunkTasks(Vec::with_capacity(chunks.len()));
+
+            for chunk in chunks {
+                let f = f.clone();
+                let chunk_task = async move { chunk.into_iter().map(|item| f(item)).collect::<Vec<_>>() };
+
+                chunk_tasks.0.push(match task_ctx.clone() {
+                    Some(task_ctx) => handler.spawn(task_ctx.scope(chunk_task)),
+                    None => handler.spawn(chunk_task),
+                });
+            }
+
+            let mut results = Vec::new();
+
+            for chunk_task in &mut chunk_tasks.0 {
+                match chunk_task.await {
+                    Ok(chunk_results) => results.extend(chunk_results),
+                    Err(err) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+                    Err(err) => panic!("Parallel chunk failed: {err}"),
+                }
+            }
+
+            results
+        };
+
+        // Parallel task not required to be `Sync`, items only moved into chunks
+        let (layer_task, handle) = self.prepare_task(task, deps, TaskOptions::default(), false);
+
+        self.handler.spawn(layer_task);
+
+        handle
+    }
+
+    /// Schedule a task on thread calling `LayersStack::update`, for example for window or surface calls.
+    /// Task takes part in dependencies graph same as worker tasks, but run only while `wait_all_blocking` drains.
+    /// Task not completed by frame deadline reported as straggler and cancelled with its layer tasks
+    pub fn schedule_main_thread<'a, T: 'static, const DEPENDENCY_COUNT: usize>(
+        &mut self,
+        task: impl Future<Output = T> + 'static,
+        deps: impl Into<Dependency<'a, DEPENDENCY_COUNT>>,
+        options: TaskOptions,
+    ) -> TaskHandle<T> {
+        let (layer_task, handle) = self.prepare_task(task, deps, options, true);
+
+        self.main_thread_tasks.push(MainThreadTask(Box::pin(layer_task)));
+
+        handle
+    }
+
+    /// Register task in frame graph, returned future runs task after dependencies in layer context.
+    /// Main thread tasks of pipelined layers ordered after previous frames, but not deferred
+    fn prepare_task<'a, T: 'static, const DEPENDENCY_COUNT: usize>(
+        &mut self,
+        task: impl Future<Output = T> + 'static,
+        deps: impl Into<Dependency<'a, DEPENDENCY_COUNT>>,
+        options: TaskOptions,
+        main_thread: bool,
+    ) -> (impl Future<Output = ()> + 'static, TaskHandle<T>) {
         let lc = self.sp.resolve::<LayerCtx>().unwrap();
 
-        self.scheduled_tasks_by_name.insert(lc.name(), lc.id());
-        
-        let (wk, wt) = signal_channel(lc.name());
-        
-        let deps = deps.into();
-
-        let deps_waiters = match deps {
-            Dependency::IdList(ids) => {
-                ids.iter()
-                    .filter_map(|id| self.scheduled_tasks.get(id).cloned())
-                    .flatten()
-                    .collect::<Vec<_>>()
-            }
-            Dependency::SizedNameList(names) => {
-                names.iter()
-                    .filter_map(|name| self.scheduled_tasks_by_name.get(*name))
-                    .filter_map(|id| self.scheduled_tasks.get(id).cloned())
-                    .flatten()
-                    .collect::<Vec<_>>()
-            }
-            Dependency::None => {
-                Vec::new()
+        let pipelined = self
+            .known_layers
+            .entry(lc.name())
+            .or_insert(KnownLayer {
+                id: lc.id(),
+                enabled: true,
+                pipelined: false,
+                updated: false,
+            })
+            .pipelined;
+
+        let (wk, wt) = self.signals.channel(&lc.name());
+
+        let deps = match deps.into() {
+            Dependency::IdList(ids) => ids.iter().copied().map(DependencyKey::Id).collect(),
+            Dependency::SizedNameList(names) => names.iter().map(|name| DependencyKey::Name(name.to_string())).collect(),
+            Dependency::Writers(names) => names.into_iter().map(DependencyKey::Optional).collect(),
+            Dependency::None => Vec::new(),
+        };
+
+        // Dependencies and access resolved on schedule or on the whole frame tasks graph, task without them started immediately
+        let (deps_sender, deps_receiver) = oneshot::channel();
+
+        let has_deps = !deps.is_empty() || !options.access.is_empty() || pipelined;
+
+        self.pending_by_layer
+            .entry(lc.id())
+            .or_default()
+            .push(self.pending_tasks.len());
+
+        self.pending_tasks.push(PendingTask {
+            layer_id: lc.id(),
+            layer_name: lc.name(),
+            deps,
+            access: options.access.clone(),
+            pipelined,
+            deps_sender: has_deps.then_some(deps_sender),
+            waiter: wt.clone(),
+        });
+
+        self.resolve_scheduled_task();
+
+        let (result_sender, result_receiver) = oneshot::channel();
+
+        let task_errors = self.task_errors.clone();
+        let (layer_id, layer_name) = (lc.id(), lc.name());
+
+        let layer_cancellation = self.layer_cancellations.entry(layer_id).or_default().clone();
+        let progress = Arc::new(Mutex::new(TaskProgress::Queued));
+
+        let task_progress = progress.clone();
+
+        let queued_at = Instant::now();
+        let profiling = self.profiler.current_frame().map(|frame| (self.profiler.clone(), frame));
+
+        let task_ctx = LayerTaskCtx {
+            id: layer_id,
+            name: layer_name.clone(),
+            frame: self.frame,
+        };
+
+        let layer_task = async move {
+            // Cancelled layer task stops waiting dependencies and never started
+            let deps_cancelled = has_deps && {
+                let mut wait_deps = pin!(async {
+                    for deps_waiter in deps_receiver.await.unwrap_or_default() {
+                        deps_waiter.wait().await;
+                    }
+                });
+                let mut cancelled = pin!(layer_cancellation.cancelled());
+
+                // Cancellation checked first, dependencies may complete by the same cancellation
+                poll_fn(|cx| {
+                    if cancelled.as_mut().poll(cx).is_ready() {
+                        return Poll::Ready(true);
+                    }
+
+                    match wait_deps.as_mut().poll(cx) {
+                        Poll::Ready(()) => Poll::Ready(false),
+                        Poll::Pending => Poll::Pending,
+                    }
+                })
+                .await
+            };
+
+            let started_at = Instant::now();
+
+            let res = match deps_cancelled {
+                true => Err((TaskErrorKind::Cancelled, "Task cancelled".to_string())),
+                false => {
+                    *task_progress.lock() = TaskProgress::Running(started_at);
+                    run_task(task, layer_cancellation, options).await
+                }
+            };
+
+            *task_progress.lock() = TaskProgress::Done;
+
+            if let Some((profiler, frame)) = profiling {
+                let task_span = TaskSpan {
+                    layer_id,
+                    layer_name: layer_name.clone(),
+                    queued: profiler.offset(queued_at),
+                    span: Span {
+                        start: profiler.offset(started_at),
+                        end: profiler.now(),
+                    },
+                };
+
+                profiler.record_task(frame, task_span);
             }
+
+            let res = res.map_err(|(kind, message)| TaskError::new(layer_id, layer_name, kind, message));
+
+            if let Err(err) = &res
+                && err.kind != TaskErrorKind::Cancelled
+            {
+                task_errors.lock().push(err.clone());
+            }
+
+            // Handle may be dropped, result not required in that case
+            _ = result_sender.send(res);
+
+            wk.signal();
+        };
+
+
+        let scheduled_task = ScheduledTask {
+            waiter: wt.clone(),
+            deferred: pipelined && !main_thread,
+            scheduled_at: queued_at,
+            progress,
         };
 
-        self.handler.spawn(
-            async move {
-                for deps_waiter in deps_waiters {
-                    deps_waiter.wait().await;
+        self.scheduled_tasks.entry(lc.id()).or_default().push(scheduled_task);
+
+        // Task sees own layer in `LayerCtx`, not last updated layer of worker thread
+        (
+            task_ctx.scope(layer_task).add_service_span(),
+            TaskHandle::new(lc.id(), wt, result_receiver),
+        )
+    }
+
+    /// Wait for all scheduled tasks to complete, render tasks of pipelined layers included.
+    /// Return error only in strict mode, if any dependency diagnostic found
+    pub fn wait_all_blocking(&mut self) -> Result<(), ScheduleError> {
+        self.wait_blocking(false)
+    }
+
+    /// Frame end wait of `LayersStack::update`, render tasks of pipelined layers left running for later frames
+    pub(crate) fn wait_frame_blocking(&mut self) -> Result<(), ScheduleError> {
+        self.wait_blocking(true)
+    }
+
+    fn wait_blocking(&mut self, frame_end: bool) -> Result<(), ScheduleError> {
+        let start = Instant::now();
+
+        let res = self.wait_scheduled_tasks(frame_end);
+
+        if self.profiler.enabled() {
+            self.profiler.record_wait(start, Instant::now());
+        }
+
+        res
+    }
+
+    fn wait_scheduled_tasks(&mut self, frame_end: bool) -> Result<(), ScheduleError> {
+        let diagnostics = self.resolve_pending_tasks();
+
+        // Waits inside frame see partial frame, so only frame end wait defers
+        if frame_end {
+            self.defer_pipelined_tasks();
+        }
+
+        if !self.scheduled_tasks.is_empty() {
+            self.wait_frame_tasks();
+        }
+
+        // Oldest frames render tasks waited after current frame tasks
+        while self.in_flight.len() > self.pipeline_depth {
+            self.wait_oldest_frame(self.frame_deadline);
+        }
+
+        self.check_diagnostics(diagnostics)
+    }
+
+    /// Wait render tasks of all frames in flight, frame deadline not applied
+    pub fn wait_pipeline_blocking(&mut self) {
+        while !self.in_flight.is_empty() {
+            self.wait_oldest_frame(None);
+        }
+    }
+
+    /// Wait all tasks ignoring frame deadline, frames in flight and removed background services.
+    /// Nothing spawned by layers runs after return, used before layers code unloaded
+    pub(crate) fn wait_unload_blocking(&mut self) -> Result<(), ScheduleError> {
+        let deadline = self.frame_deadline.take();
+
+        let res = self.wait_all_blocking();
+
+        self.frame_deadline = deadline;
+
+        self.wait_pipeline_blocking();
+        self.background.wait_removed_blocking(&self.handler);
+
+        res
+    }
+
+    /// Wait render tasks of oldest frame in flight, tasks not completed by `deadline` from frame start reported as stragglers
+    fn wait_oldest_frame(&mut self, deadline: Option<Duration>) {
+        let Some(tasks) = self.in_flight.pop_front() else {
+            return;
+        };
+
+        let wait_all = async {
+            for (_, layer_tasks) in &tasks {
+                for task in layer_tasks {
+                    task.waiter.wait_ref().await;
                 }
+            }
+        };
+
+        match deadline {
+            Some(deadline) => {
+                let frame_start = self.frame_start.unwrap_or_else(Instant::now);
+                let remaining = deadline.saturating_sub(frame_start.elapsed());
 
-                task.await;
+                let completed =
+                    self.handler.block_on(async { tokio::time::timeout(remaining, wait_all).await.is_ok() });
 
-                wk.signal().await;
+                if !completed {
+                    self.report_stragglers(deadline, tasks);
+                }
             }
-            .add_service_span(),
-        );
+            None => self.handler.block_on(wait_all),
+        }
 
-        self.scheduled_tasks
-            .entry(lc.id())
-            .and_modify(|waters| waters.push(wt.clone()))
-            .or_insert(Vec::from([wt.clone()]));
+        if self.in_flight.is_empty() {
+            self.pipeline_tail.clear();
+        }
+    }
+
+    /// Move render tasks of pipelined layers out of current frame wait
+    fn defer_pipelined_tasks(&mut self) {
+        let mut deferred = Vec::new();
 
-        wt
+        for (layer_id, tasks) in &mut self.scheduled_tasks {
+            let layer_deferred = tasks.extract_if(.., |task| task.deferred).collect::<Vec<_>>();
+
+            if !layer_deferred.is_empty() {
+                deferred.push((*layer_id, layer_deferred));
+            }
+        }
+
+        if deferred.is_empty() {
+            return;
+        }
+
+        self.scheduled_tasks.retain(|_, tasks| !tasks.is_empty());
+
+        self.pipeline_tail = deferred
+            .iter()
+            .flat_map(|(_, tasks)| tasks)
+            .map(|task| task.waiter.clone())
+            .collect();
+        self.in_flight.push_back(deferred);
     }
 
-    /// Wait for all scheduled tasks to complete
-    pub fn wait_all_blocking(&mut self) {
-        if self.scheduled_tasks.is_empty() {
+    fn wait_frame_tasks(&mut self) {
+        let mut scheduled_tasks = self.scheduled_tasks.drain().collect::<Vec<_>>();
+        let mut main_thread_tasks = std::mem::take(&mut self.main_thread_tasks);
+
+        let wait_all = async {
+            tracing::debug!("Block on waiting {count} tasks", count = scheduled_tasks.len());
+
+            let mut wait_tasks = pin!(async {
+                for (layer_id, tasks) in &mut scheduled_tasks {
+                    for task in tasks {
+                        task.waiter.wait_ref().await;
+                    }
+
+                    tracing::debug!("{layer_id} wait completed");
+                }
+            });
+
+            let mut waited = false;
+
+            // Main thread tasks polled on blocked thread between waits of worker tasks
+            poll_fn(|cx| {
+                main_thread_tasks.retain_mut(|task| task.0.as_mut().poll(cx).is_pending());
+
+                if !waited {
+                    waited = wait_tasks.as_mut().poll(cx).is_ready();
+                }
+
+                match waited && main_thread_tasks.is_empty() {
+                    true => Poll::Ready(()),
+                    false => Poll::Pending,
+                }
+            })
+            .await
+        };
+
+        let Some(deadline) = self.frame_deadline else {
+            self.handler.block_on(wait_all);
+            return;
+        };
+
+        let frame_start = self.frame_start.unwrap_or_else(Instant::now);
+        let remaining = deadline.saturating_sub(frame_start.elapsed());
+
+        let completed = self.handler.block_on(async { tokio::time::timeout(remaining, wait_all).await.is_ok() });
+
+        if completed {
+            return;
+        }
+
+        self.report_stragglers(deadline, scheduled_tasks);
+
+        // Layers of pending main thread tasks cancelled, so tasks complete on single poll
+        self.handler.block_on(poll_fn(|cx| {
+            main_thread_tasks.retain_mut(|task| task.0.as_mut().poll(cx).is_pending());
+            Poll::Ready(())
+        }));
+
+        // Dropped task completes own signal, result handle gets lost task error
+        if !main_thread_tasks.is_empty() {
+            tracing::warn!(
+                "{count} main thread tasks dropped after frame deadline",
+                count = main_thread_tasks.len()
+            );
+        }
+    }
+
+    /// Cancel not completed tasks layers and save report, waiters of stragglers dropped
+    fn report_stragglers(&mut self, deadline: Duration, scheduled_tasks: Vec<(LayerId, Vec<ScheduledTask>)>) {
+        let now = Instant::now();
+
+        let mut report = self.straggler_report.take().unwrap_or(StragglerReport {
+            deadline,
+            tasks: Vec::new(),
+        });
+
+        for (layer_id, tasks) in scheduled_tasks {
+            let layer_name = tasks.first().map(|task| task.waiter.name().to_string()).unwrap_or_default();
+
+            let mut has_stragglers = false;
+
+            for task in tasks {
+                let (started, elapsed) = match *task.progress.lock() {
+                    TaskProgress::Queued => (false, now - task.scheduled_at),
+                    TaskProgress::Running(started_at) => (true, now - started_at),
+                    TaskProgress::Done => continue,
+                };
+
+                has_stragglers = true;
+
+                tracing::warn!("[{layer_name}] <{layer_id}> Task missed frame deadline, {elapsed:?} elapsed");
+
+                report.tasks.push(StragglerTask {
+                    layer_id,
+                    layer_name: layer_name.clone(),
+                    started,
+                    elapsed,
+                });
+            }
+
+            if has_stragglers {
+                self.cancel_tasks_of(layer_id);
+            }
+        }
+
+        self.straggler_report = Some(report);
+    }
+
+    /// Take panicked tasks errors collected since last call
+    pub(crate) fn take_task_errors(&self) -> Vec<TaskError> {
+        std::mem::take(&mut *self.task_errors.lock())
+    }
+
+    fn check_diagnostics(&self, diagnostics: Vec<ScheduleDiagnostic>) -> Result<(), ScheduleError> {
+        if self.strict && !diagnostics.is_empty() {
+            return Err(ScheduleError { diagnostics });
+        }
+
+        Ok(())
+    }
+
+    /// Send dependency waiters to last scheduled task, if all its dependencies are updated layers.
+    /// Edges of tasks resolved on schedule point only to earlier tasks, so frame graph cycles never include them
+    fn resolve_scheduled_task(&mut self) {
+        if self.graph_open {
+            return;
+        }
+
+        let idx = self.pending_tasks.len() - 1;
+        let task = &self.pending_tasks[idx];
+
+        let resolvable = !task.pipelined
+            && task.access.is_empty()
+            && task.deps.iter().all(|dep| {
+                let known_layer = match dep {
+                    DependencyKey::Id(id) => self.known_layers.values().find(|layer| layer.id == *id),
+                    DependencyKey::Name(name) => self.known_layers.get(name),
+                    DependencyKey::Optional(_) => None,
+                };
+
+                known_layer.is_some_and(|layer| layer.updated || layer.id == task.layer_id)
+            });
+
+        if !resolvable {
+            self.graph_open = true;
             return;
         }
 
-        self.handler.block_on(async {
-            tracing::debug!("Block on waiting {count} tasks", count = self.scheduled_tasks.len());
+        let mut diagnostics = std::mem::take(&mut self.resolved_diagnostics);
+
+        let task_edges = self.task_edges(&self.pending_tasks, &self.pending_by_layer, idx, &mut diagnostics);
+
+        self.resolved_diagnostics = diagnostics;
+
+        if let Some(deps_sender) = self.pending_tasks[idx].deps_sender.take() {
+            let waiters = task_edges
+                .iter()
+                .map(|dep_idx| self.pending_tasks[*dep_idx].waiter.clone())
+                .collect();
+
+            // Task already finished if receiver dropped
+            _ = deps_sender.send(waiters);
+        }
+
+        self.resolved_edges.push(task_edges);
+    }
+
+    fn task_edges(
+        &self,
+        tasks: &[PendingTask],
+        tasks_by_layer: &HashMap<LayerId, Vec<usize>, ahash::RandomState>,
+        idx: usize,
+        diagnostics: &mut Vec<ScheduleDiagnostic>,
+    ) -> Vec<usize> {
+        let task = &tasks[idx];
+
+        let mut task_edges = Vec::new();
+
+        for dep in &task.deps {
+            let Some(dep_id) = self.validate_dependency(task, dep, diagnostics) else {
+                continue;
+            };
+
+            let dep_tasks = tasks_by_layer.get(&dep_id).map(Vec::as_slice).unwrap_or_default();
+
+            // Dependency on own layer means all earlier tasks of the layer
+            task_edges.extend(dep_tasks.iter().copied().filter(|dep_idx| dep_id != task.layer_id || *dep_idx < idx));
+        }
+
+        task_edges.sort_unstable();
+        task_edges.dedup();
+
+        task_edges
+    }
+
+    /// Build tasks graph, validate dependencies, break cycles and send dependency waiters to pending tasks
+    fn resolve_pending_tasks(&mut self) -> Vec<ScheduleDiagnostic> {
+        let mut tasks = std::mem::take(&mut self.pending_tasks);
+        let tasks_by_layer = std::mem::take(&mut self.pending_by_layer);
+        let mut diagnostics = std::mem::take(&mut self.resolved_diagnostics);
+        let mut edges = std::mem::take(&mut self.resolved_edges);
+
+        self.graph_open = false;
+
+        let resolved = edges.len();
+
+        for idx in resolved..tasks.len() {
+            let task_edges = self.task_edges(&tasks, &tasks_by_layer, idx, &mut diagnostics);
+            edges.push(task_edges);
+        }
+
+        while let Some(cycle) = find_cycle(&edges) {
+            let layers = cycle.iter().map(|idx| tasks[*idx].layer_name.clone()).collect::<Vec<_>>();
+
+            // Drop edge which close the cycle
+            let (last, first) = (cycle[cycle.len() - 1], cycle[0]);
+            edges[last].retain(|dep_idx| *dep_idx != first);
+
+            diagnostics.push(ScheduleDiagnostic::Cycle { layers });
+        }
+
+        order_conflicting_access(&tasks, &mut edges, &mut diagnostics);
+
+        let deps_senders = tasks.iter_mut().map(|task| task.deps_sender.take()).collect::<Vec<_>>();
+
+        for (idx, (deps_sender, task_edges)) in deps_senders.into_iter().zip(edges).enumerate() {
+            let Some(deps_sender) = deps_sender else {
+                continue;
+            };
+
+            let mut waiters = task_edges
+                .into_iter()
+                .map(|dep_idx| tasks[dep_idx].waiter.clone())
+                .collect::<Vec<_>>();
+
+            // Render tasks of frame run after render tasks of previous frame
+            if tasks[idx].pipelined {
+                waiters.extend(self.pipeline_tail.iter().cloned());
+            }
+
+            // Task already finished if receiver dropped
+            _ = deps_sender.send(waiters);
+        }
+
+        for diagnostic in &diagnostics {
+            tracing::warn!("{diagnostic}");
+        }
+
+        self.diagnostics.extend(diagnostics.iter().cloned());
+
+        diagnostics
+    }
+
+    fn validate_dependency(
+        &self,
+        task: &PendingTask,
+        dep: &DependencyKey,
+        diagnostics: &mut Vec<ScheduleDiagnostic>,
+    ) -> Option<LayerId> {
+        let known_layer = match dep {
+            DependencyKey::Id(id) => self.known_layers.values().find(|layer| layer.id == *id),
+            DependencyKey::Name(name) => self.known_layers.get(name),
+            DependencyKey::Optional(name) => {
+                return self
+                    .known_layers
+                    .get(name)
+                    .filter(|layer| layer.enabled)
+                    .map(|layer| layer.id);
+            }
+        };
+
+        let Some(known_layer) = known_layer else {
+            diagnostics.push(ScheduleDiagnostic::UnknownDependency {
+                layer: task.layer_name.clone(),
+                dependency: dep.to_string(),
+            });
+            return None;
+        };
+
+        if !known_layer.enabled {
+            diagnostics.push(ScheduleDiagnostic::DisabledDependency {
+                layer: task.layer_name.clone(),
+                dependency: dep.to_string(),
+            });
+            return None;
+        }
+
+        Some(known_layer.id)
+    }
+}
+
+/// Find any cycle in dependency graph, return tasks indices in dependency order
+fn find_cycle(edges: &[Vec<usize>]) -> Option<Vec<usize>> {
+    let mut deps_left = edges.iter().map(Vec::len).collect::<Vec<_>>();
+    let mut dependents = vec![Vec::new(); edges.len()];
+
+    for (idx, task_edges) in edges.iter().enumerate() {
+        for dep_idx in task_edges {
+            dependents[*dep_idx].push(idx);
+        }
+    }
+
+    let mut ready = (0..edges.len()).filter(|idx| deps_left[*idx] == 0).collect::<Vec<_>>();
+    let mut done = vec![false; edges.len()];
+
+    while let Some(idx) = ready.pop() {
+        done[idx] = true;
+
+        for dependent in &dependents[idx] {
+            deps_left[*dependent] -= 1;
+
+            if deps_left[*dependent] == 0 {
+                ready.push(*dependent);
+            }
+        }
+    }
+
+    // Every not done task has not done dependency, so walk over them until repeat
+    let start = done.iter().position(|done| !done)?;
+
+    let mut path = vec![start];
+    let mut current = start;
+
+    loop {
+        current = *edges[current].iter().find(|dep_idx| !done[**dep_idx]).unwrap();
+
+        if let Some(pos) = path.iter().position(|idx| *idx == current) {
+            return Some(path.split_off(pos));
+        }
+
+        path.push(current);
+    }
+}
+
+/// Order not ordered tasks with conflicting access by schedule order, earlier task first.
+/// Tasks of every resource chained once: reader after last writer, writer after readers since last writer
+fn order_conflicting_access(tasks: &[PendingTask], edges: &mut [Vec<usize>], diagnostics: &mut Vec<ScheduleDiagnostic>) {
+    let mut by_resource = BTreeMap::<TypeInfo, Vec<(usize, bool)>>::new();
+
+    for (idx, task) in tasks.iter().enumerate() {
+        for access in &task.access {
+            by_resource.entry(access.resource).or_default().push((idx, access.write));
+        }
+    }
+
+    if by_resource.is_empty() {
+        return;
+    }
+
+    // Dependencies may order tasks against schedule order, so chains follow graph order
+    let rank = graph_order(edges);
+
+    for (resource, mut group) in by_resource {
+        group.sort_unstable_by_key(|(idx, _)| rank[*idx]);
+
+        let mut last_writer = None;
+        let mut readers = Vec::new();
 
-            for (layer_id, waiters) in self.scheduled_tasks.drain() {
-                for waiter in waiters {
-                    waiter.wait().await;
+        for (second, write) in group {
+            let firsts = match write {
+                true if !readers.is_empty() => std::mem::take(&mut readers),
+                _ => last_writer.into_iter().collect(),
+            };
+
+            for first in firsts {
+                if depends_on(edges, second, first) {
+                    continue;
                 }
-                
-                tracing::debug!("{layer_id} wait completed");
+
+                edges[second].push(first);
+
+                diagnostics.push(ScheduleDiagnostic::AccessConflict {
+                    resource: resource.name.to_string(),
+                    first: tasks[first].layer_name.clone(),
+                    second: tasks[second].layer_name.clone(),
+                });
             }
-        });
+
+            match write {
+                true => last_writer = Some(second),
+                false => readers.push(second),
+            }
+        }
+    }
+}
+
+/// Rank of tasks in acyclic graph order, not ordered tasks ranked by schedule order
+fn graph_order(edges: &[Vec<usize>]) -> Vec<usize> {
+    let mut deps_left = edges.iter().map(Vec::len).collect::<Vec<_>>();
+    let mut dependents = vec![Vec::new(); edges.len()];
+
+    for (idx, task_edges) in edges.iter().enumerate() {
+        for dep_idx in task_edges {
+            dependents[*dep_idx].push(idx);
+        }
+    }
+
+    let mut ready = (0..edges.len())
+        .filter(|idx| deps_left[*idx] == 0)
+        .map(Reverse)
+        .collect::<BinaryHeap<_>>();
+
+    let mut rank = vec![0; edges.len()];
+    let mut next = 0;
+
+    while let Some(Reverse(idx)) = ready.pop() {
+        rank[idx] = next;
+        next += 1;
+
+        for dependent in &dependents[idx] {
+            deps_left[*dependent] -= 1;
+
+            if deps_left[*dependent] == 0 {
+                ready.push(Reverse(*dependent));
+            }
+        }
+    }
+
+    rank
+}
+
+/// Task transitively depends on another task
+fn depends_on(edges: &[Vec<usize>], task: usize, dependency: usize) -> bool {
+    let mut visited = vec![false; edges.len()];
+    let mut stack = vec![task];
+
+    while let Some(idx) = stack.pop() {
+        for dep_idx in &edges[idx] {
+            if *dep_idx == dependency {
+                return true;
+            }
+
+            if !visited[*dep_idx] {
+                visited[*dep_idx] = true;
+                stack.push(*dep_idx);
+            }
+        }
+    }
+
+    false
+}
+
+/// Split items into consecutive chunks, last chunk may be shorter
+fn split_chunks<T>(items: Vec<T>, chunk_size: usize) -> Vec<Vec<T>> {
+    let mut chunks = Vec::with_capacity(items.len().div_ceil(chunk_size));
+    let mut items = items.into_iter();
+
+    while items.len() > 0 {
+        chunks.push(items.by_ref().take(chunk_size).collect());
+    }
+
+    chunks
+}
+
+/// Chunks of parallel task, aborted if task cancelled or panicked
+struct ChunkTasks<R>(Vec<JoinHandle<Vec<R>>>);
+
+impl<R> Drop for ChunkTasks<R> {
+    fn drop(&mut self) {
+        for chunk_task in &self.0 {
+            chunk_task.abort();
+        }
+    }
+}
+
+/// Race task with cancellation and timeout, catch task panic
+pub(crate) async fn run_task<T>(
+    task: impl Future<Output = T>,
+    layer_cancellation: CancellationToken,
+    options: TaskOptions,
+) -> Result<T, (TaskErrorKind, String)> {
+    let mut task = pin!(CatchUnwind::new(task));
+    let mut layer_cancelled = pin!(layer_cancellation.cancelled());
+
+    let cancellation = options.cancellation.unwrap_or_default();
+    let mut cancelled = pin!(cancellation.cancelled());
+
+    let mut timeout = options.timeout.map(|timeout| Box::pin(tokio::time::sleep(timeout)));
+
+    poll_fn(|cx| {
+        if let Poll::Ready(res) = task.as_mut().poll(cx) {
+            return Poll::Ready(res.map_err(|panic| (TaskErrorKind::Panic, panic_message(&*panic))));
+        }
+
+        if layer_cancelled.as_mut().poll(cx).is_ready() || cancelled.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(Err((TaskErrorKind::Cancelled, "Task cancelled".to_string())));
+        }
+
+        if let Some(timeout) = &mut timeout
+            && timeout.as_mut().poll(cx).is_ready()
+        {
+            return Poll::Ready(Err((TaskErrorKind::TimedOut, "Task timed out".to_string())));
+        }
+
+        Poll::Pending
+    })
+    .await
+}
+
+struct MainThreadTask(Pin<Box<dyn Future<Output = ()>>>);
+
+impl std::fmt::Debug for MainThreadTask {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("MainThreadTask").finish()
+    }
+}
+
+#[derive(Debug)]
+struct ScheduledTask {
+    waiter: Waiter,
+    /// Waited by later frame, see `LayerScheduler::set_pipeline_depth`
+    deferred: bool,
+    scheduled_at: Instant,
+    progress: Arc<Mutex<TaskProgress>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum TaskProgress {
+    Queued,
+    Running(Instant),
+    Done,
+}
+
+#[derive(Debug)]
+struct KnownLayer {
+    id: LayerId,
+    enabled: bool,
+    pipelined: bool,
+    /// `on_update` of layer completed in current frame
+    updated: bool,
+}
+
+#[derive(Debug)]
+struct PendingTask {
+    layer_id: LayerId,
+    layer_name: String,
+    deps: Vec<DependencyKey>,
+    access: Vec<ResourceAccess>,
+    pipelined: bool,
+    deps_sender: Option<oneshot::Sender<Vec<Waiter>>>,
+    waiter: Waiter,
+}
+
+#[derive(Debug)]
+enum DependencyKey {
+    Id(LayerId),
+    Name(String),
+    /// Skipped if layer unknown or disabled
+    Optional(String),
+}
+
+impl std::fmt::Display for DependencyKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DependencyKey::Id(id) => write!(f, "{id}"),
+            DependencyKey::Name(name) | DependencyKey::Optional(name) => write!(f, "{name}"),
+        }
     }
 }
 
 pub enum Dependency<'a, const N: usize> {
     IdList(&'a [LayerId]),
     SizedNameList([&'a str; N]),
+    /// Events writers layers, unknown and disabled layers ignored without diagnostic
+    Writers(Vec<String>),
     None,
 }
 
@@ -127,4 +1217,4 @@ impl<'a> From<()> for Dependency<'a, 0> {
     fn from(_: ()) -> Self {
         Dependency::None
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+
+/// Frame stage of layer, stages updated in declaration order.
+/// Inside stage layers ordered by `before`/`after` constraints, then by registration order
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    First,
+    PreUpdate,
+    #[default]
+    Update,
+    PostUpdate,
+    PreRender,
+    Render,
+    Last,
+}
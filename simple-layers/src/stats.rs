@@ -0,0 +1,252 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use chrono::TimeDelta;
+use parking_lot::Mutex;
+use xdi::{ServiceProvider, types::error::ServiceBuildResult};
+
+const DEFAULT_WINDOW_LEN: usize = 120;
+const DEFAULT_SMOOTHING: f64 = 0.1;
+
+/// Frames time statistics, updated once per root stack frame by `LayersStack::update`.
+/// Frames with zero dt (first frame) counted but not included into timings
+#[derive(Debug, Clone)]
+pub struct FrameStats {
+    inner: Arc<Mutex<FrameStatsInner>>,
+    in_frame: Arc<AtomicBool>,
+}
+
+#[derive(Debug)]
+struct FrameStatsInner {
+    frame: u64,
+    dt: TimeDelta,
+    smoothed_dt: Option<TimeDelta>,
+    smoothing: f64,
+    window_len: usize,
+    window: VecDeque<TimeDelta>,
+    log_interval: Option<TimeDelta>,
+    log_elapsed: TimeDelta,
+}
+
+/// Statistics snapshot, min, max and percentiles over window of last frames
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameStatsSummary {
+    pub frame: u64,
+    pub dt: TimeDelta,
+    /// Exponential moving average of dt
+    pub smoothed_dt: TimeDelta,
+    pub min_dt: TimeDelta,
+    pub max_dt: TimeDelta,
+    /// Frames per second by smoothed dt
+    pub fps: f64,
+    pub p50: TimeDelta,
+    pub p95: TimeDelta,
+    pub p99: TimeDelta,
+}
+
+impl std::fmt::Display for FrameStatsSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ms = |dt: TimeDelta| dt.num_microseconds().unwrap_or(i64::MAX) as f64 / 1000.0;
+
+        write!(
+            f,
+            "frame {}: {:.1} fps, dt {:.2}ms (smoothed {:.2}ms, min {:.2}ms, max {:.2}ms, p50 {:.2}ms, p95 {:.2}ms, p99 {:.2}ms)",
+            self.frame,
+            self.fps,
+            ms(self.dt),
+            ms(self.smoothed_dt),
+            ms(self.min_dt),
+            ms(self.max_dt),
+            ms(self.p50),
+            ms(self.p95),
+            ms(self.p99),
+        )
+    }
+}
+
+impl FrameStats {
+    pub fn new(_: ServiceProvider) -> ServiceBuildResult<Self> {
+        Ok(Self {
+            inner: Arc::new(Mutex::new(FrameStatsInner {
+                frame: 0,
+                dt: TimeDelta::zero(),
+                smoothed_dt: None,
+                smoothing: DEFAULT_SMOOTHING,
+                window_len: DEFAULT_WINDOW_LEN,
+                window: Default::default(),
+                log_interval: None,
+                log_elapsed: TimeDelta::zero(),
+            })),
+            in_frame: Default::default(),
+        })
+    }
+
+    /// Frames count, current frame index while frame updated
+    pub fn frame(&self) -> u64 {
+        self.inner.lock().frame
+    }
+
+    /// Last frame dt
+    pub fn dt(&self) -> TimeDelta {
+        self.inner.lock().dt
+    }
+
+    pub fn smoothed_dt(&self) -> TimeDelta {
+        self.inner.lock().smoothed_dt.unwrap_or_default()
+    }
+
+    /// Frames per second by smoothed dt, zero before first timed frame
+    pub fn fps(&self) -> f64 {
+        fps(self.smoothed_dt())
+    }
+
+    pub fn summary(&self) -> FrameStatsSummary {
+        let inner = self.inner.lock();
+
+        let mut window = inner.window.iter().copied().collect::<Vec<_>>();
+        window.sort();
+
+        let smoothed_dt = inner.smoothed_dt.unwrap_or_default();
+
+        FrameStatsSummary {
+            frame: inner.frame,
+            dt: inner.dt,
+            smoothed_dt,
+            min_dt: window.first().copied().unwrap_or_default(),
+            max_dt: window.last().copied().unwrap_or_default(),
+            fps: fps(smoothed_dt),
+            p50: percentile(&window, 50),
+            p95: percentile(&window, 95),
+            p99: percentile(&window, 99),
+        }
+    }
+
+    /// Moving average factor in `(0, 1]`, greater values follow dt changes faster
+    pub fn set_smoothing(&self, smoothing: f64) {
+        assert!(
+            smoothing > 0.0 && smoothing <= 1.0,
+            "Smoothing factor should be in (0, 1]"
+        );
+
+        self.inner.lock().smoothing = smoothing;
+    }
+
+    /// Frames count of min, max and percentiles window
+    pub fn set_window_len(&self, window_len: usize) {
+        assert!(window_len > 0, "Stats window should not be empty");
+
+        let mut inner = self.inner.lock();
+
+        inner.window_len = window_len;
+
+        while inner.window.len() > window_len {
+            inner.window.pop_front();
+        }
+    }
+
+    /// Log summary every interval of frames time, `None` disables logging
+    pub fn set_log_interval(&self, interval: Option<TimeDelta>) {
+        let mut inner = self.inner.lock();
+
+        inner.log_interval = interval;
+        inner.log_elapsed = TimeDelta::zero();
+    }
+
+    /// Drop collected timings, frames count kept
+    pub fn reset(&self) {
+        let mut inner = self.inner.lock();
+
+        inner.dt = TimeDelta::zero();
+        inner.smoothed_dt = None;
+        inner.window.clear();
+        inner.log_elapsed = TimeDelta::zero();
+    }
+
+    /// Return false if frame already started by parent stack
+    pub(crate) fn begin_frame(&self, dt: &TimeDelta) -> bool {
+        if self.in_frame.swap(true, Ordering::AcqRel) {
+            return false;
+        }
+
+        let log = {
+            let mut inner = self.inner.lock();
+
+            inner.frame += 1;
+            inner.dt = *dt;
+
+            if *dt <= TimeDelta::zero() {
+                return true;
+            }
+
+            inner.record(*dt)
+        };
+
+        if log {
+            tracing::info!("{}", self.summary());
+        }
+
+        true
+    }
+
+    pub(crate) fn end_frame(&self) {
+        self.in_frame.store(false, Ordering::Release);
+    }
+}
+
+impl FrameStatsInner {
+    /// Return true if summary should be logged
+    fn record(&mut self, dt: TimeDelta) -> bool {
+        self.smoothed_dt = Some(match self.smoothed_dt {
+            Some(smoothed) => {
+                let smoothed = smoothed.num_nanoseconds().unwrap_or(i64::MAX) as f64;
+                let dt = dt.num_nanoseconds().unwrap_or(i64::MAX) as f64;
+
+                TimeDelta::nanoseconds((smoothed + self.smoothing * (dt - smoothed)) as i64)
+            }
+            None => dt,
+        });
+
+        if self.window.len() >= self.window_len {
+            self.window.pop_front();
+        }
+
+        self.window.push_back(dt);
+
+        let Some(interval) = self.log_interval else {
+            return false;
+        };
+
+        self.log_elapsed += dt;
+
+        if self.log_elapsed < interval {
+            return false;
+        }
+
+        self.log_elapsed = TimeDelta::zero();
+
+        true
+    }
+}
+
+fn fps(dt: TimeDelta) -> f64 {
+    match dt.num_nanoseconds() {
+        Some(nanos) if nanos > 0 => 1_000_000_000.0 / nanos as f64,
+        _ => 0.0,
+    }
+}
+
+/// Nearest rank percentile of sorted values
+fn percentile(sorted: &[TimeDelta], percent: usize) -> TimeDelta {
+    if sorted.is_empty() {
+        return TimeDelta::zero();
+    }
+
+    let rank = (percent * sorted.len()).div_ceil(100).max(1);
+
+    sorted[rank - 1]
+}
@@ -0,0 +1,350 @@
+use std::{
+    ops::{Deref, DerefMut},
+    sync::Arc,
+};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use parking_lot::Mutex;
+use tokio::runtime::{Builder, Runtime};
+use xdi::{ServiceProvider, builder::DiBuilder, types::error::ServiceBuildResult};
+
+use crate::{
+    ILayersSystemDependencies,
+    layer::{ILayersSource, LayerCtx, LayersStack},
+    profiler::{FrameProfile, Span},
+    stats::FrameStats,
+    time::Clock,
+};
+
+const DEFAULT_WORKER_THREADS: usize = 2;
+
+type Registration = Box<dyn FnOnce(&DiBuilder)>;
+
+/// `TestStack` setup, runtime and system dependencies registered by `build`
+pub struct TestStackBuilder {
+    worker_threads: usize,
+    clock_start: DateTime<Utc>,
+    profiler: bool,
+    registrations: Vec<Registration>,
+    sources: Vec<fn(&mut LayersStack)>,
+}
+
+impl Default for TestStackBuilder {
+    fn default() -> Self {
+        Self {
+            worker_threads: DEFAULT_WORKER_THREADS,
+            clock_start: DateTime::UNIX_EPOCH,
+            profiler: true,
+            registrations: Vec::new(),
+            sources: Vec::new(),
+        }
+    }
+}
+
+impl TestStackBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_worker_threads(mut self, worker_threads: usize) -> Self {
+        self.worker_threads = worker_threads;
+        self
+    }
+
+    /// Manual clock start time, unix epoch by default
+    pub fn with_clock_start(mut self, start: DateTime<Utc>) -> Self {
+        self.clock_start = start;
+        self
+    }
+
+    /// Record frame profiles, enabled by default. Layers and tasks order assertions require profiler
+    pub fn with_profiler(mut self, enabled: bool) -> Self {
+        self.profiler = enabled;
+        self
+    }
+
+    /// Register services used by tested layers, called after system dependencies so
+    /// registrations replace them
+    pub fn register(mut self, registration: impl FnOnce(&DiBuilder) + 'static) -> Self {
+        self.registrations.push(Box::new(registration));
+        self
+    }
+
+    /// Register source layers on build
+    pub fn with_source<TLayersSource: ILayersSource>(mut self) -> Self {
+        self.sources.push(TLayersSource::register);
+        self
+    }
+
+    pub fn build(self) -> TestStack {
+        let runtime = Builder::new_multi_thread()
+            .worker_threads(self.worker_threads)
+            .enable_time()
+            .build()
+            .expect("Test stack runtime build failed");
+
+        let builder = DiBuilder::new();
+
+        let handle = runtime.handle().clone();
+        let clock = Clock::manual(self.clock_start);
+
+        builder.singletone(move |_| Ok(handle.clone()));
+        builder.register_layers_system_dependencies();
+        builder.singletone(move |_| Ok(clock.clone()));
+        builder.singletone(Recorder::new);
+
+        for registration in self.registrations {
+            registration(&builder);
+        }
+
+        let sp = builder.build();
+
+        let mut stack = sp.resolve::<LayersStack>().expect("Test stack resolve failed");
+        let recorder = sp.resolve::<Recorder>().expect("Test recorder resolve failed");
+
+        // Frame profiles are source of layers and tasks order assertions
+        stack.profiler().set_enabled(self.profiler);
+
+        for register in self.sources {
+            register(&mut stack);
+        }
+
+        TestStack {
+            stack,
+            recorder,
+            sp,
+            runtime,
+        }
+    }
+}
+
+/// Layers stack with own runtime and manual clock for layers unit tests.
+/// Layers and tasks of every frame recorded by profiler and checked by `assert_*` methods.
+/// Stack shut down on drop
+pub struct TestStack {
+    stack: LayersStack,
+    recorder: Recorder,
+    sp: ServiceProvider,
+    /// Dropped after stack, so shutdown on drop still able to run tasks
+    runtime: Runtime,
+}
+
+impl TestStack {
+    pub fn builder() -> TestStackBuilder {
+        TestStackBuilder::new()
+    }
+
+    pub fn sp(&self) -> &ServiceProvider {
+        &self.sp
+    }
+
+    pub fn runtime(&self) -> &Runtime {
+        &self.runtime
+    }
+
+    /// Recorder singleton, resolved by tested layers as `Recorder`
+    pub fn recorder(&self) -> &Recorder {
+        &self.recorder
+    }
+
+    /// Advance manual clock by `dt` and update stack, panics on stack error
+    pub fn frame(&mut self, dt: TimeDelta) {
+        self.stack.step(dt).expect("Test stack frame failed");
+    }
+
+    /// Run `count` frames of `dt`, panics on stack error
+    pub fn frames(&mut self, count: usize, dt: TimeDelta) {
+        self.stack.run_frames(count, dt).expect("Test stack frames failed");
+    }
+
+    /// Last recorded frame profile, panics if no frames run
+    pub fn last_frame(&self) -> FrameProfile {
+        self.stack.profiler().last_frame().expect("No test stack frames recorded")
+    }
+
+    /// Names of layers updated in last frame, in update order
+    pub fn updated_layers(&self) -> Vec<String> {
+        self.last_frame()
+            .layers
+            .into_iter()
+            .map(|layer| layer.layer_name)
+            .collect()
+    }
+
+    /// Assert layers updated in last frame in given relative order
+    pub fn assert_layers_order(&self, layers: &[&str]) {
+        let updated = self.updated_layers();
+
+        assert_ordered(&updated, layers, "Layers update order");
+    }
+
+    /// Assert layer updated in last frame
+    pub fn assert_updated(&self, layer: &str) {
+        let updated = self.updated_layers();
+
+        assert!(
+            updated.iter().any(|name| name == layer),
+            "Layer [{layer}] not updated, updated layers {updated:?}"
+        );
+    }
+
+    /// Assert layer not updated in last frame
+    pub fn assert_not_updated(&self, layer: &str) {
+        let updated = self.updated_layers();
+
+        assert!(
+            !updated.iter().any(|name| name == layer),
+            "Layer [{layer}] updated, updated layers {updated:?}"
+        );
+    }
+
+    /// Assert all last frame tasks of layer `first` completed before any task of layer `second` started
+    pub fn assert_tasks_before(&self, first: &str, second: &str) {
+        let frame = self.last_frame();
+
+        let first_spans = task_spans(&frame, first);
+        let second_spans = task_spans(&frame, second);
+
+        let first_end = first_spans.iter().map(|span| span.end).max().unwrap();
+        let second_start = second_spans.iter().map(|span| span.start).min().unwrap();
+
+        assert!(
+            first_end <= second_start,
+            "Tasks of layer [{second}] started {:?} before tasks of layer [{first}] completed",
+            first_end - second_start
+        );
+    }
+
+    /// Assert count of layer tasks run in last frame
+    pub fn assert_tasks_count(&self, layer: &str, count: usize) {
+        let frame = self.last_frame();
+
+        let tasks = frame.tasks.iter().filter(|task| task.layer_name == layer).count();
+
+        assert_eq!(tasks, count, "Tasks count of layer [{layer}]");
+    }
+}
+
+impl Deref for TestStack {
+    type Target = LayersStack;
+
+    fn deref(&self) -> &Self::Target {
+        &self.stack
+    }
+}
+
+impl DerefMut for TestStack {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.stack
+    }
+}
+
+impl Drop for TestStack {
+    fn drop(&mut self) {
+        // Failed test not panicking again from layers hooks
+        if !std::thread::panicking() {
+            self.stack.shutdown();
+        }
+    }
+}
+
+impl std::fmt::Debug for TestStack {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TestStack")
+            .field("stack", &self.stack)
+            .field("recorder", &self.recorder)
+            .finish()
+    }
+}
+
+fn task_spans(frame: &FrameProfile, layer: &str) -> Vec<Span> {
+    let spans = frame
+        .tasks
+        .iter()
+        .filter(|task| task.layer_name == layer)
+        .map(|task| task.span)
+        .collect::<Vec<_>>();
+
+    assert!(!spans.is_empty(), "No tasks of layer [{layer}] in frame {}", frame.index);
+
+    spans
+}
+
+/// Assert `expected` items found in `items` in the same order, other items ignored
+fn assert_ordered(items: &[String], expected: &[&str], what: &str) {
+    let mut rest = items.iter();
+
+    for item in expected {
+        assert!(
+            rest.any(|other| other == item),
+            "{what} mismatch, [{item}] missing or out of order in {items:?}, expected {expected:?}"
+        );
+    }
+}
+
+/// Event recorded by layer or task
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedEvent {
+    pub label: String,
+    /// Recording layer, empty outside of layers and tasks
+    pub layer: String,
+    /// Root frame index of recording
+    pub frame: u64,
+}
+
+/// Execution order recorder, shared by clones
+#[derive(Clone)]
+pub struct Recorder {
+    events: Arc<Mutex<Vec<RecordedEvent>>>,
+    ctx: LayerCtx,
+    stats: FrameStats,
+}
+
+impl std::fmt::Debug for Recorder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Recorder")
+            .field("events", &self.events.lock().len())
+            .finish()
+    }
+}
+
+impl Recorder {
+    pub fn new(sp: ServiceProvider) -> ServiceBuildResult<Self> {
+        Ok(Self {
+            events: Default::default(),
+            ctx: sp.resolve()?,
+            stats: sp.resolve()?,
+        })
+    }
+
+    /// Record event with current layer, task layer inside scheduled tasks
+    pub fn record(&self, label: impl Into<String>) {
+        self.events.lock().push(RecordedEvent {
+            label: label.into(),
+            layer: self.ctx.name(),
+            frame: self.stats.frame(),
+        });
+    }
+
+    pub fn events(&self) -> Vec<RecordedEvent> {
+        self.events.lock().clone()
+    }
+
+    pub fn labels(&self) -> Vec<String> {
+        self.events.lock().iter().map(|event| event.label.clone()).collect()
+    }
+
+    /// Take recorded events, recorder cleared
+    pub fn take(&self) -> Vec<RecordedEvent> {
+        std::mem::take(&mut *self.events.lock())
+    }
+
+    pub fn clear(&self) {
+        self.events.lock().clear();
+    }
+
+    /// Assert labels recorded in given relative order
+    pub fn assert_order(&self, labels: &[&str]) {
+        assert_ordered(&self.labels(), labels, "Recorded order");
+    }
+}
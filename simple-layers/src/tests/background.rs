@@ -0,0 +1,140 @@
+use std::{
+    sync::mpsc::{self, Receiver as SyncReceiver, Sender as SyncSender},
+    time::{Duration, Instant},
+};
+
+use xdi::{ServiceProvider, types::error::ServiceBuildResult};
+
+use crate::{
+    background::{BackgroundOptions, BackgroundServiceInfo, BackgroundState, RestartPolicy},
+    layer::{ILayer, LayersStack},
+    scheduler::LayerScheduler,
+};
+
+use super::testing::sender_stack;
+
+/// Streams values from background service to frame loop
+#[derive(Debug)]
+pub struct StreamingLayer {
+    fail: bool,
+    stream: Option<SyncReceiver<i32>>,
+    sender: SyncSender<i32>,
+}
+
+impl StreamingLayer {
+    pub fn new(fail: bool, sp: ServiceProvider) -> ServiceBuildResult<Self> {
+        Ok(Self {
+            fail,
+            stream: None,
+            sender: sp.resolve()?,
+        })
+    }
+}
+
+impl ILayer for StreamingLayer {
+    fn on_attach(&mut self, scheduler: &mut LayerScheduler) {
+        let (tx, rx) = mpsc::channel();
+
+        self.stream = Some(rx);
+
+        let fail = self.fail;
+
+        scheduler.spawn_background(
+            "stream",
+            move |cancellation| {
+                let tx = tx.clone();
+
+                async move {
+                    anyhow::ensure!(!fail, "stream failed");
+
+                    for value in 0.. {
+                        if cancellation.is_cancelled() || tx.send(value).is_err() {
+                            break;
+                        }
+
+                        tokio::time::sleep(Duration::from_millis(1)).await;
+                    }
+
+                    Ok(())
+                }
+            },
+            BackgroundOptions::new().with_restart(RestartPolicy::OnFailure { attempts: 2 }),
+        );
+    }
+
+    fn on_update(&mut self, _dt: &chrono::TimeDelta, _scheduler: &mut LayerScheduler) -> anyhow::Result<()> {
+        if let Some(stream) = &self.stream {
+            self.sender.send(stream.try_iter().count() as i32).unwrap();
+        }
+
+        Ok(())
+    }
+}
+
+/// Wait until service state matches
+fn wait_service(stack: &LayersStack, check: impl Fn(&BackgroundServiceInfo) -> bool) -> BackgroundServiceInfo {
+    let start = Instant::now();
+
+    loop {
+        let services = stack.background_services();
+
+        if let [service] = services.as_slice()
+            && check(service)
+        {
+            return service.clone();
+        }
+
+        assert!(start.elapsed() < Duration::from_secs(5), "Unexpected services state {services:?}");
+
+        std::thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[test]
+fn background_service_lifecycle_ok() {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).build();
+
+    let layer_id = stack.push_layer("streaming", |sp| Ok(StreamingLayer::new(false, sp)?)).id();
+
+    stack.update().unwrap();
+
+    // Service lives across frames
+    std::thread::sleep(Duration::from_millis(20));
+    stack.update().unwrap();
+
+    assert!(rx.try_iter().sum::<i32>() > 0);
+
+    let service = wait_service(&stack, |service| service.state == BackgroundState::Running);
+
+    assert_eq!((service.layer_name.as_str(), service.name.as_str()), ("streaming", "stream"));
+
+    stack.disable(layer_id);
+
+    wait_service(&stack, |service| service.state == BackgroundState::Stopped);
+
+    stack.enable(layer_id);
+
+    wait_service(&stack, |service| service.state == BackgroundState::Running);
+
+    stack.remove_layer(layer_id).unwrap();
+
+    assert!(stack.background_services().is_empty());
+}
+
+#[test]
+fn background_service_restart_ok() {
+    let (tx, _rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).build();
+
+    stack.push_layer("streaming", |sp| Ok(StreamingLayer::new(true, sp)?));
+
+    stack.update().unwrap();
+
+    let service = wait_service(&stack, |service| service.state == BackgroundState::Failed);
+
+    assert_eq!(service.restarts, 2);
+    assert!(service.last_error.unwrap().contains("stream failed"));
+}
@@ -0,0 +1,91 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+    mpsc,
+};
+
+use chrono::TimeDelta;
+
+use crate::{condition::RunCondition, profiler::FrameProfiler};
+
+use super::{layer::DtLayer, testing::sender_stack};
+
+#[test]
+fn run_condition_every_nth_frame_ok() {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).build();
+
+    stack
+        .push_layer("dt", |sp| Ok(DtLayer::new(sp)?))
+        .with_run_condition(RunCondition::every_nth_frame(3));
+
+    stack.run_frames(7, TimeDelta::milliseconds(10)).unwrap();
+
+    // Frames 1, 4 and 7, skipped frames time not accumulated
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [10, 10, 10]);
+}
+
+#[test]
+fn run_condition_max_rate_ok() {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).build();
+
+    stack
+        .push_layer("dt", |sp| Ok(DtLayer::new(sp)?))
+        .with_run_condition(RunCondition::max_rate(50));
+
+    stack.run_frames(6, TimeDelta::milliseconds(10)).unwrap();
+
+    assert_eq!(rx.try_iter().count(), 3);
+
+    // Long frame runs layer once
+    stack.step(TimeDelta::milliseconds(100)).unwrap();
+    stack.step(TimeDelta::milliseconds(10)).unwrap();
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [100]);
+}
+
+#[test]
+fn run_condition_predicates_ok() {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).with_profiler(false).build();
+
+    let allowed = Arc::new(AtomicBool::new(true));
+
+    let predicate_allowed = allowed.clone();
+
+    stack
+        .push_layer("dt", |sp| Ok(DtLayer::new(sp)?))
+        .with_run_condition(RunCondition::run_if(move |_| predicate_allowed.load(Ordering::Relaxed)))
+        .with_run_condition(RunCondition::resource_exists::<FrameProfiler>(|profiler| profiler.enabled()));
+
+    stack
+        .push_layer("not_registered", |sp| Ok(DtLayer::new(sp)?))
+        .with_run_condition(RunCondition::resource_exists::<String>(|_| true));
+
+    stack.step(TimeDelta::milliseconds(10)).unwrap();
+
+    assert_eq!(rx.try_iter().count(), 0);
+
+    stack.profiler().set_enabled(true);
+    stack.step(TimeDelta::milliseconds(20)).unwrap();
+
+    allowed.store(false, Ordering::Relaxed);
+    stack.step(TimeDelta::milliseconds(30)).unwrap();
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [20]);
+
+    // Skipped layers not recorded in profile
+    let frame = stack.profiler().last_frame().unwrap();
+
+    assert!(frame.layers.is_empty());
+}
+
+#[test]
+#[should_panic(expected = "Run rate should be positive")]
+fn run_condition_zero_rate_err() {
+    RunCondition::max_rate(0);
+}
@@ -0,0 +1,206 @@
+use serde::Deserialize;
+use std::sync::mpsc;
+
+use crate::{
+    config::{LayersRegistry, LayersStackConfig},
+    layer::LayersStack,
+    types::error::LayersConfigError,
+};
+
+use super::{layer::Layer, testing::sender_stack};
+
+#[derive(Debug, Deserialize)]
+struct LayerParams {
+    data: i32,
+}
+
+const CONFIG: &str = r#"
+[[layers]]
+name = "0"
+
+[[layers]]
+name = "debug_1"
+layer = "data"
+group = "debug"
+params = { data = 1 }
+
+[[layers]]
+name = "2"
+layer = "data"
+enabled = false
+params = { data = 2 }
+
+[[layers]]
+name = "debug_3"
+layer = "data"
+group = "debug"
+params = { data = 3 }
+
+[profiles.release]
+disable = ["debug"]
+
+[profiles.headless]
+enable = ["2"]
+exclude = ["debug"]
+"#;
+
+fn build_registry() -> LayersRegistry {
+    let mut registry = LayersRegistry::new();
+
+    registry
+        .register("0", |sp| Ok(Layer::new(0, sp)?))
+        .register_with_params("data", |sp, params: &LayerParams| Ok(Layer::new(params.data, sp)?));
+
+    registry
+}
+
+fn run_profile(profile: Option<&str>) -> Vec<i32> {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).build();
+
+    let config = LayersStackConfig::from_toml(CONFIG).unwrap();
+
+    stack.apply_config(&build_registry(), &config, profile).unwrap();
+
+    stack.update().unwrap();
+
+    rx.try_iter().collect()
+}
+
+#[test]
+fn config_apply_ok() {
+    assert_eq!(run_profile(None), [0, 1, 3]);
+}
+
+#[test]
+fn config_release_profile_ok() {
+    assert_eq!(run_profile(Some("release")), [0]);
+}
+
+#[test]
+fn config_headless_profile_ok() {
+    assert_eq!(run_profile(Some("headless")), [0, 2]);
+}
+
+#[test]
+fn config_json_groups_ok() {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).build();
+
+    let config = LayersStackConfig::from_json(
+        r#"{ "layers": [
+            { "name": "0" },
+            { "name": "1", "layer": "data", "group": "debug", "params": { "data": 1 } }
+        ] }"#,
+    )
+    .unwrap();
+
+    stack.apply_config(&build_registry(), &config, None).unwrap();
+
+    stack.disable_group("debug").unwrap();
+
+    stack.update().unwrap();
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [0]);
+}
+
+#[test]
+fn config_stages_ok() {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).build();
+
+    let config = LayersStackConfig::from_toml(
+        r#"
+        [[layers]]
+        name = "render"
+        layer = "data"
+        stage = "render"
+        params = { data = 3 }
+
+        [[layers]]
+        name = "late"
+        layer = "data"
+        after = ["0"]
+        params = { data = 2 }
+
+        [[layers]]
+        name = "0"
+
+        [[layers]]
+        name = "first"
+        layer = "data"
+        stage = "first"
+        params = { data = 1 }
+        "#,
+    )
+    .unwrap();
+
+    stack.apply_config(&build_registry(), &config, None).unwrap();
+
+    stack.update().unwrap();
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [1, 0, 2, 3]);
+}
+
+#[test]
+fn config_validation_err() {
+    let (tx, _rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).build();
+    let registry = build_registry();
+
+    let apply = |stack: &mut LayersStack, config: &str, profile| {
+        let config = LayersStackConfig::from_toml(config)?;
+        stack.apply_config(&registry, &config, profile)
+    };
+
+    assert_eq!(
+        apply(&mut stack, "[[layers]]\nname = \"unknown\"", None),
+        Err(LayersConfigError::UnknownLayer {
+            layer: "unknown".to_string(),
+            ty: "unknown".to_string(),
+        })
+    );
+
+    assert_eq!(
+        apply(&mut stack, "[[layers]]\nname = \"0\"\n[[layers]]\nname = \"0\"", None),
+        Err(LayersConfigError::DuplicateLayerName { name: "0".to_string() })
+    );
+
+    assert!(matches!(
+        apply(&mut stack, "[[layers]]\nname = \"0\"\n[[layers]]\nname = \"1\"\nlayer = \"data\"", None),
+        Err(LayersConfigError::InvalidParams { layer, .. }) if layer == "1"
+    ));
+
+    assert_eq!(
+        apply(&mut stack, "[[layers]]\nname = \"0\"\nafter = [\"input\"]", None),
+        Err(LayersConfigError::UnknownOrderTarget {
+            layer: "0".to_string(),
+            target: "input".to_string(),
+        })
+    );
+
+    assert_eq!(
+        apply(&mut stack, CONFIG, Some("dev")),
+        Err(LayersConfigError::UnknownProfile { profile: "dev".to_string() })
+    );
+
+    assert_eq!(
+        apply(&mut stack, "[profiles.dev]\ndisable = [\"render\"]", Some("dev")),
+        Err(LayersConfigError::UnknownProfileTarget {
+            profile: "dev".to_string(),
+            target: "render".to_string(),
+        })
+    );
+
+    assert!(matches!(
+        apply(&mut stack, "[[layer]]\nname = \"0\"", None),
+        Err(LayersConfigError::Parse { .. })
+    ));
+
+    // Stack not changed by failed configs
+    assert_eq!(stack.layers().count(), 0);
+}
@@ -0,0 +1,159 @@
+use parking_lot::Mutex;
+use std::{
+    sync::{
+        Arc,
+        mpsc::{self, Sender as SyncSender},
+    },
+    time::Duration,
+};
+use xdi::{ServiceProvider, types::error::ServiceBuildResult};
+
+use crate::{
+    events::{EventReader, EventWriter, Events, ILayersEventsDependencies},
+    layer::ILayer,
+    scheduler::LayerScheduler,
+};
+
+use super::testing::sender_stack;
+
+#[derive(Debug)]
+pub struct WriterLayer {
+    frame: i32,
+    delay: Option<Duration>,
+    writer: EventWriter<i32>,
+}
+
+impl WriterLayer {
+    pub fn new(delay: Option<Duration>, sp: ServiceProvider) -> ServiceBuildResult<Self> {
+        Ok(Self {
+            frame: 0,
+            delay,
+            writer: sp.resolve::<Events<i32>>()?.writer(),
+        })
+    }
+}
+
+impl ILayer for WriterLayer {
+    fn on_update(&mut self, _dt: &chrono::TimeDelta, scheduler: &mut LayerScheduler) -> anyhow::Result<()> {
+        self.frame += 1;
+
+        let event = self.frame * 10;
+
+        let Some(delay) = self.delay else {
+            self.writer.send(event);
+            return Ok(());
+        };
+
+        let writer = self.writer.clone();
+
+        scheduler.schedule(
+            async move {
+                std::thread::sleep(delay);
+                writer.send(event);
+            },
+            (),
+        );
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct ReaderLayer {
+    /// Read in task after writers tasks
+    in_task: bool,
+    reader: Arc<Mutex<EventReader<i32>>>,
+    sender: SyncSender<Vec<i32>>,
+}
+
+impl ReaderLayer {
+    pub fn new(in_task: bool, sp: ServiceProvider) -> ServiceBuildResult<Self> {
+        Ok(Self {
+            in_task,
+            reader: Arc::new(Mutex::new(sp.resolve::<Events<i32>>()?.reader())),
+            sender: sp.resolve()?,
+        })
+    }
+}
+
+impl ILayer for ReaderLayer {
+    fn on_update(&mut self, _dt: &chrono::TimeDelta, scheduler: &mut LayerScheduler) -> anyhow::Result<()> {
+        if !self.in_task {
+            self.sender.send(self.reader.lock().read()).unwrap();
+            return Ok(());
+        }
+
+        let reader = self.reader.clone();
+        let sender = self.sender.clone();
+
+        let deps = &*self.reader.lock();
+
+        scheduler.schedule(
+            async move {
+                sender.send(reader.lock().read()).unwrap();
+            },
+            deps,
+        );
+
+        Ok(())
+    }
+}
+
+#[test]
+fn events_double_buffering_ok() {
+    let (tx, rx) = mpsc::channel::<Vec<i32>>();
+
+    let mut stack = sender_stack(tx)
+        .register(|builder| builder.register_events::<i32>())
+        .build();
+
+    stack.push_layer("reader_before", |sp| Ok(ReaderLayer::new(false, sp)?));
+    stack.push_layer("writer", |sp| Ok(WriterLayer::new(None, sp)?));
+    stack.push_layer("reader_after", |sp| Ok(ReaderLayer::new(false, sp)?));
+
+    stack.update().unwrap();
+    stack.update().unwrap();
+
+    // Earlier reader see writer events on next frame
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [vec![], vec![10], vec![10], vec![20]]);
+
+    let reader_id = stack.get_layer("reader_before").unwrap().id();
+
+    stack.disable(reader_id);
+
+    for _ in 0..3 {
+        stack.update().unwrap();
+    }
+
+    stack.enable(reader_id);
+
+    stack.update().unwrap();
+
+    // Events older than two frames dropped
+    assert_eq!(
+        rx.try_iter().collect::<Vec<_>>(),
+        [vec![30], vec![40], vec![50], vec![50], vec![60]]
+    );
+}
+
+#[test]
+fn events_reader_depends_on_writers_ok() {
+    let (tx, rx) = mpsc::channel::<Vec<i32>>();
+
+    let mut stack = sender_stack(tx)
+        .register(|builder| builder.register_events::<i32>())
+        .build();
+
+    stack.push_layer("reader", |sp| Ok(ReaderLayer::new(true, sp)?));
+    stack.push_layer("writer", |sp| Ok(WriterLayer::new(Some(Duration::from_millis(20)), sp)?));
+    stack.push_layer("disabled_writer", |sp| Ok(WriterLayer::new(None, sp)?)).disable();
+
+    // Writers registered on first update, reader task resolved before writer created
+    stack.update().unwrap();
+    rx.try_iter().for_each(drop);
+
+    stack.update().unwrap();
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [vec![10, 20]]);
+    assert!(stack.scheduler().diagnostics().is_empty());
+}
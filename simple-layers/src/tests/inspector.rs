@@ -0,0 +1,156 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::TcpStream,
+    sync::mpsc,
+    time::Duration,
+};
+
+use serde_json::{Value, json};
+
+use crate::{
+    inspector::{InspectorEndpoint, LayerStateKind, layer_infos},
+    layer::LayersStack,
+    stage::Stage,
+    types::error::InspectorError,
+};
+
+use super::{layer::Layer, testing::sender_stack};
+
+fn connect(stack: &mut LayersStack) -> BufReader<TcpStream> {
+    let endpoint = stack
+        .open_inspector("127.0.0.1:0".parse().unwrap())
+        .unwrap()
+        .endpoint()
+        .clone();
+
+    let InspectorEndpoint::Tcp(addr) = endpoint else {
+        panic!("Tcp endpoint expected");
+    };
+
+    let client = TcpStream::connect(addr).unwrap();
+    client.set_read_timeout(Some(Duration::from_millis(10))).unwrap();
+
+    BufReader::new(client)
+}
+
+/// Send request and update stack until response received
+fn request(stack: &mut LayersStack, client: &mut BufReader<TcpStream>, request: &str) -> Value {
+    writeln!(client.get_mut(), "{request}").unwrap();
+
+    let mut line = String::new();
+
+    for _ in 0..500 {
+        stack.update().unwrap();
+
+        _ = client.read_line(&mut line);
+
+        if line.ends_with('\n') {
+            return serde_json::from_str(&line).unwrap();
+        }
+    }
+
+    panic!("No inspector response for {request}");
+}
+
+#[test]
+fn inspector_endpoint_parse_ok() {
+    assert_eq!(
+        "127.0.0.1:7000".parse::<InspectorEndpoint>().unwrap(),
+        InspectorEndpoint::Tcp("127.0.0.1:7000".parse().unwrap())
+    );
+    assert_eq!(
+        "unix:/tmp/layers.sock".parse::<InspectorEndpoint>().unwrap(),
+        InspectorEndpoint::Unix("/tmp/layers.sock".into())
+    );
+    assert!("localhost".parse::<InspectorEndpoint>().is_err());
+    assert!(matches!(
+        "0.0.0.0:7000".parse::<InspectorEndpoint>().unwrap_err(),
+        InspectorError::NotLoopback { endpoint } if endpoint == "0.0.0.0:7000"
+    ));
+    assert_eq!(
+        "[::1]:7000".parse::<InspectorEndpoint>().unwrap(),
+        InspectorEndpoint::Tcp("[::1]:7000".parse().unwrap())
+    );
+}
+
+#[test]
+fn inspector_layers_control_ok() {
+    let (tx, _rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).build();
+
+    stack.push_layer("0", |sp| Ok(Layer::new(0, sp)?));
+    stack.push_layer("1", |sp| Ok(Layer::new(1, sp)?)).disable();
+
+    assert!(matches!(
+        stack.open_inspector(InspectorEndpoint::Tcp("0.0.0.0:0".parse().unwrap())).unwrap_err(),
+        InspectorError::NotLoopback { .. }
+    ));
+
+    let mut client = connect(&mut stack);
+
+    let res = request(&mut stack, &mut client, r#"{"cmd":"layers"}"#);
+
+    assert_eq!(res["ok"], true);
+    assert_eq!(res["result"][0]["name"], "0");
+    assert_eq!(res["result"][0]["state"], "created");
+    assert_eq!(res["result"][0]["stage"], "update");
+    assert_eq!(res["result"][1]["state"], "pending");
+    assert_eq!(res["result"][1]["enabled"], false);
+    assert!(res["result"][1]["ty"].as_str().unwrap().ends_with("tests::layer::Layer"));
+
+    let res = request(&mut stack, &mut client, r#"{"cmd":"enable","name":"1"}"#);
+
+    assert_eq!(res, json!({ "ok": true, "result": null }));
+    assert!(stack.get_layer("1").unwrap().is_created());
+
+    let res = request(&mut stack, &mut client, r#"{"cmd":"disable","name":"0"}"#);
+
+    assert_eq!(res["ok"], true);
+    assert!(!stack.get_layer("0").unwrap().enabled());
+
+    let infos = layer_infos(&stack);
+
+    assert_eq!(infos[1].state, LayerStateKind::Created);
+    assert_eq!(infos[1].stage, Stage::Update);
+
+    let res = request(&mut stack, &mut client, r#"{"cmd":"enable","name":"2"}"#);
+
+    assert_eq!(res, json!({ "ok": false, "error": "Layer [2] not found" }));
+
+    let res = request(&mut stack, &mut client, r#"{"cmd":"unknown"}"#);
+
+    assert_eq!(res["ok"], false);
+}
+
+#[test]
+fn inspector_stats_and_trace_ok() {
+    let (tx, _rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).with_profiler(false).build();
+
+    stack.push_layer("0", |sp| Ok(Layer::new(0, sp)?));
+
+    let mut client = connect(&mut stack);
+
+    let res = request(&mut stack, &mut client, r#"{"cmd":"stats"}"#);
+
+    assert_eq!(res["ok"], true);
+    assert!(res["result"]["frame"].as_u64().unwrap() > 0);
+    assert!(res["result"]["fps"].is_number());
+
+    let res = request(&mut stack, &mut client, r#"{"cmd":"trace"}"#);
+
+    let events = res["result"]["traceEvents"].as_array().unwrap();
+
+    assert!(events.iter().any(|event| event["cat"] == "frame"));
+    assert!(events.iter().any(|event| event["cat"] == "layer" && event["name"] == "0"));
+
+    // Profiler enabled only for traced frame
+    assert!(!stack.profiler().enabled());
+    assert_eq!(stack.profiler().history().len(), 1);
+
+    stack.close_inspector();
+
+    assert!(stack.inspector().is_none());
+}
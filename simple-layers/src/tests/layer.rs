@@ -1,15 +1,26 @@
 use async_broadcast::Sender as AsyncSender;
-use std::sync::mpsc::{self, Sender as SyncSender};
-use tokio::runtime::Builder;
-use xdi::{ServiceProvider, builder::DiBuilder, types::error::ServiceBuildResult};
+use parking_lot::Mutex;
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+    mpsc::{self, Sender as SyncSender},
+};
+use chrono::{TimeDelta, Utc};
+use xdi::{ServiceProvider, types::error::ServiceBuildResult};
 
 use crate::{
-    ILayersSystemDependencies,
-    layer::{ILayer, LayersStack},
+    time::FixedTimestep,
+    layer::{ILayer, ILayersSource, LayerErrorPolicy, LayerPosition, LayersStack},
     scheduler::LayerScheduler,
-    types::id::LayerId,
+    testing::TestStack,
+    types::{
+        error::{LayerErrorStage, LayersStackError},
+        id::LayerId,
+    },
 };
 
+use super::{scheduler::DepLayer, testing::sender_stack};
+
 #[derive(Debug)]
 pub struct Layer {
     data: i32,
@@ -26,8 +37,10 @@ impl Layer {
 }
 
 impl ILayer for Layer {
-    fn on_update(&mut self, _dt: &chrono::TimeDelta, _scheduler: &mut LayerScheduler) {
+    fn on_update(&mut self, _dt: &chrono::TimeDelta, _scheduler: &mut LayerScheduler) -> anyhow::Result<()> {
         self.sender.send(self.data).unwrap();
+
+        Ok(())
     }
 }
 
@@ -47,7 +60,7 @@ impl AsyncLayer {
 }
 
 impl ILayer for AsyncLayer {
-    fn on_update(&mut self, _dt: &chrono::TimeDelta, scheduler: &mut LayerScheduler) {
+    fn on_update(&mut self, _dt: &chrono::TimeDelta, scheduler: &mut LayerScheduler) -> anyhow::Result<()> {
         let sender = self.sender.clone();
         let data = self.data;
 
@@ -57,6 +70,8 @@ impl ILayer for AsyncLayer {
             },
             (),
         );
+
+        Ok(())
     }
 }
 
@@ -78,7 +93,7 @@ impl AsyncLayerWithDep {
 }
 
 impl ILayer for AsyncLayerWithDep {
-    fn on_update(&mut self, _dt: &chrono::TimeDelta, scheduler: &mut LayerScheduler) {
+    fn on_update(&mut self, _dt: &chrono::TimeDelta, scheduler: &mut LayerScheduler) -> anyhow::Result<()> {
         let sender = self.sender.clone();
         let data = self.data;
 
@@ -88,31 +103,95 @@ impl ILayer for AsyncLayerWithDep {
             },
             self.dep.as_slice(),
         );
+
+        Ok(())
     }
 }
 
-#[test]
-fn layers_stack_update_ok() {
-    let runtime = Builder::new_multi_thread()
-        .worker_threads(4)
-        .build()
-        .unwrap();
+#[derive(Debug)]
+pub struct LifecycleLayer {
+    sender: SyncSender<&'static str>,
+}
 
-    let (tx, rx) = mpsc::channel::<i32>();
+impl LifecycleLayer {
+    pub fn new(sp: ServiceProvider) -> ServiceBuildResult<Self> {
+        Ok(Self {
+            sender: sp.resolve()?,
+        })
+    }
+}
 
-    let builder = DiBuilder::new();
+impl ILayer for LifecycleLayer {
+    fn on_attach(&mut self, _scheduler: &mut LayerScheduler) {
+        self.sender.send("attach").unwrap();
+    }
 
-    let handler = runtime.handle().clone();
+    fn on_update(&mut self, _dt: &chrono::TimeDelta, _scheduler: &mut LayerScheduler) -> anyhow::Result<()> {
+        self.sender.send("update").unwrap();
 
-    builder.singletone(move |_| Ok(handler.clone()));
+        Ok(())
+    }
 
-    builder.thread_local(move |_| Ok(tx.clone()));
+    fn on_enable(&mut self, _scheduler: &mut LayerScheduler) {
+        self.sender.send("enable").unwrap();
+    }
 
-    builder.register_layers_system_dependencies();
+    fn on_disable(&mut self, _scheduler: &mut LayerScheduler) {
+        self.sender.send("disable").unwrap();
+    }
 
-    let sp = builder.build();
+    fn on_detach(&mut self, _scheduler: &mut LayerScheduler) {
+        self.sender.send("detach").unwrap();
+    }
 
-    let mut stack = sp.resolve::<LayersStack>().unwrap();
+    fn on_shutdown(&mut self, _scheduler: &mut LayerScheduler) {
+        self.sender.send("shutdown").unwrap();
+    }
+}
+
+#[derive(Debug)]
+pub struct FailingLayer;
+
+impl ILayer for FailingLayer {
+    fn on_update(&mut self, _dt: &chrono::TimeDelta, _scheduler: &mut LayerScheduler) -> anyhow::Result<()> {
+        anyhow::bail!("update failed")
+    }
+}
+
+/// Send frame dt in milliseconds
+#[derive(Debug)]
+pub struct DtLayer {
+    sender: SyncSender<i32>,
+}
+
+impl DtLayer {
+    pub fn new(sp: ServiceProvider) -> ServiceBuildResult<Self> {
+        Ok(Self { sender: sp.resolve()? })
+    }
+}
+
+impl ILayer for DtLayer {
+    fn on_update(&mut self, dt: &chrono::TimeDelta, _scheduler: &mut LayerScheduler) -> anyhow::Result<()> {
+        self.sender.send(dt.num_milliseconds() as i32).unwrap();
+
+        Ok(())
+    }
+}
+
+pub struct TestLayersSource;
+
+impl ILayersSource for TestLayersSource {
+    fn register(layers_stack: &mut LayersStack) {
+        layers_stack.push_layer("10", |sp| Ok(Layer::new(10, sp)?));
+        layers_stack.push_layer("11", |sp| Ok(Layer::new(11, sp)?));
+    }
+}
+
+#[test]
+fn layers_stack_update_ok() {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).build();
 
     stack.push_layer("0", |sp| Ok(Layer::new(0, sp)?));
 
@@ -120,7 +199,7 @@ fn layers_stack_update_ok() {
 
     stack.push_layer("2", |sp| Ok(Layer::new(2, sp)?));
 
-    stack.update();
+    stack.update().unwrap();
 
     assert_eq!(rx.recv().unwrap(), 0);
     assert_eq!(rx.recv().unwrap(), 1);
@@ -129,26 +208,9 @@ fn layers_stack_update_ok() {
 
 #[test]
 fn layers_stack_disable_update_ok() {
-    let runtime = Builder::new_multi_thread()
-        .worker_threads(4)
-        .build()
-        .unwrap();
-
     let (tx, rx) = mpsc::channel::<i32>();
 
-    let builder = DiBuilder::new();
-
-    let handler = runtime.handle().clone();
-
-    builder.singletone(move |_| Ok(handler.clone()));
-
-    builder.thread_local(move |_| Ok(tx.clone()));
-
-    builder.register_layers_system_dependencies();
-
-    let sp = builder.build();
-
-    let mut stack = sp.resolve::<LayersStack>().unwrap();
+    let mut stack = sender_stack(tx).build();
 
     stack.push_layer("0", |sp| Ok(Layer::new(0, sp)?));
 
@@ -156,7 +218,7 @@ fn layers_stack_disable_update_ok() {
 
     stack.push_layer("2", |sp| Ok(Layer::new(2, sp)?));
 
-    stack.update();
+    stack.update().unwrap();
 
     assert_eq!(rx.recv().unwrap(), 0);
     assert_eq!(rx.recv().unwrap(), 2);
@@ -164,26 +226,13 @@ fn layers_stack_disable_update_ok() {
 
 #[test]
 fn layers_stack_update_with_async_task_ok() {
-    let runtime = Builder::new_multi_thread()
-        .worker_threads(4)
-        .build()
-        .unwrap();
-
     let (tx, mut rx) = async_broadcast::broadcast::<i32>(3);
 
-    let builder = DiBuilder::new();
-
-    let handler = runtime.handle().clone();
-
-    builder.singletone(move |_| Ok(handler.clone()));
-
-    builder.thread_local(move |_| Ok(tx.clone()));
-
-    builder.register_layers_system_dependencies();
-
-    let sp = builder.build();
-
-    let mut stack = sp.resolve::<LayersStack>().unwrap();
+    let mut stack = TestStack::builder()
+        .register(move |builder| {
+            builder.thread_local(move |_| Ok(tx.clone()));
+        })
+        .build();
 
     stack.push_layer("0", |sp| Ok(AsyncLayer::new(0, sp)?));
 
@@ -191,10 +240,10 @@ fn layers_stack_update_with_async_task_ok() {
 
     stack.push_layer("2", |sp| Ok(AsyncLayer::new(2, sp)?));
 
-    stack.update();
+    stack.update().unwrap();
 
-    runtime.block_on(async move {
-        let res = vec![
+    stack.runtime().block_on(async move {
+        let res = [
             rx.recv().await.unwrap(),
             rx.recv().await.unwrap(),
             rx.recv().await.unwrap(),
@@ -207,26 +256,13 @@ fn layers_stack_update_with_async_task_ok() {
 
 #[test]
 fn layers_stack_update_with_async_task_with_dep_ok() {
-    let runtime = Builder::new_multi_thread()
-        .worker_threads(4)
-        .build()
-        .unwrap();
-
     let (tx, mut rx) = async_broadcast::broadcast::<i32>(3);
 
-    let builder = DiBuilder::new();
-
-    let handler = runtime.handle().clone();
-
-    builder.singletone(move |_| Ok(handler.clone()));
-
-    builder.thread_local(move |_| Ok(tx.clone()));
-
-    builder.register_layers_system_dependencies();
-
-    let sp = builder.build();
-
-    let mut stack = sp.resolve::<LayersStack>().unwrap();
+    let mut stack = TestStack::builder()
+        .register(move |builder| {
+            builder.thread_local(move |_| Ok(tx.clone()));
+        })
+        .build();
 
     let layer_id = stack.push_layer("0", |sp| Ok(AsyncLayerWithDep::new(0, vec![], sp)?)).id();
 
@@ -240,11 +276,430 @@ fn layers_stack_update_with_async_task_with_dep_ok() {
         move |sp| Ok(AsyncLayerWithDep::new(2, vec![layer_id], sp)?),
     ).id();
 
-    stack.update();
+    stack.update().unwrap();
 
-    runtime.block_on(async move {
+    stack.runtime().block_on(async move {
         assert_eq!(rx.recv().await.unwrap(), 0);
         assert_eq!(rx.recv().await.unwrap(), 1);
         assert_eq!(rx.recv().await.unwrap(), 2);
     });
 }
+
+#[test]
+fn layers_stack_lifecycle_hooks_ok() {
+    let (tx, rx) = mpsc::channel::<&'static str>();
+
+    let mut stack = sender_stack(tx).build();
+
+    let layer_id = stack.push_layer("0", |sp| Ok(LifecycleLayer::new(sp)?)).id();
+
+    // not created yet, hooks skipped
+    stack.disable(layer_id);
+    stack.enable(layer_id);
+
+    stack.update().unwrap();
+
+    stack.disable(layer_id);
+    stack.disable(layer_id);
+
+    stack.update().unwrap();
+
+    stack.enable(layer_id);
+
+    stack.update().unwrap();
+
+    stack.shutdown();
+
+    // shut down layer not created again
+    stack.update().unwrap();
+
+    assert!(stack.get_layer("0").unwrap().is_shutdown());
+    assert_eq!(
+        rx.try_iter().collect::<Vec<_>>(),
+        ["attach", "update", "disable", "enable", "update", "shutdown", "detach"]
+    );
+}
+
+#[test]
+fn layers_stack_error_policy_log_ok() {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).build();
+
+    let errors = Arc::new(Mutex::new(Vec::new()));
+
+    let callback_errors = errors.clone();
+    stack.set_error_callback(move |err| callback_errors.lock().push(err.stage));
+
+    stack.push_layer("0", |_| anyhow::Result::<Layer>::Err(anyhow::anyhow!("build failed")));
+
+    let failing_id = stack.push_layer("1", |_| Ok(FailingLayer)).id();
+
+    stack.push_layer("2", |sp| Ok(Layer::new(2, sp)?));
+
+    stack.update().unwrap();
+    stack.update().unwrap();
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [2, 2]);
+
+    assert_eq!(
+        *errors.lock(),
+        [
+            LayerErrorStage::Build,
+            LayerErrorStage::Update,
+            LayerErrorStage::Build,
+            LayerErrorStage::Update,
+        ]
+    );
+
+    assert_eq!(stack.layer_errors(failing_id).unwrap().len(), 2);
+}
+
+#[test]
+fn layers_stack_error_policy_disable_ok() {
+    let (tx, _rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).build();
+
+    let failing_id = stack
+        .push_layer("0", |_| Ok(FailingLayer))
+        .with_error_policy(LayerErrorPolicy::Disable)
+        .id();
+
+    stack.update().unwrap();
+    stack.update().unwrap();
+
+    assert_eq!(stack.layer_errors(failing_id).unwrap().len(), 1);
+}
+
+#[test]
+fn layers_stack_error_policy_retry_ok() {
+    let (tx, _rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).build();
+
+    let build_count = Arc::new(AtomicUsize::new(0));
+
+    let ctr_build_count = build_count.clone();
+
+    let failing_id = stack
+        .push_layer("0", move |_| {
+            ctr_build_count.fetch_add(1, Ordering::SeqCst);
+            Ok(FailingLayer)
+        })
+        .with_error_policy(LayerErrorPolicy::Retry { attempts: 2 })
+        .id();
+
+    for _ in 0..5 {
+        stack.update().unwrap();
+    }
+
+    assert_eq!(build_count.load(Ordering::SeqCst), 3);
+    assert_eq!(stack.layer_errors(failing_id).unwrap().len(), 3);
+}
+
+#[test]
+fn layers_stack_error_policy_abort_ok() {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).build();
+
+    stack.push_layer("0", |sp| Ok(Layer::new(0, sp)?));
+
+    stack
+        .push_layer("1", |_| Ok(FailingLayer))
+        .with_error_policy(LayerErrorPolicy::Abort);
+
+    stack.push_layer("2", |sp| Ok(Layer::new(2, sp)?));
+
+    let LayersStackError::Layer(err) = stack.update().unwrap_err() else {
+        panic!("Expected layer error");
+    };
+
+    assert_eq!(err.name, "1");
+    assert_eq!(err.stage, LayerErrorStage::Update);
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [0]);
+}
+
+#[test]
+fn layers_stack_error_time_from_clock_ok() {
+    let (tx, _rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).build();
+
+    stack.clock().set_manual();
+
+    let failing_id = stack.push_layer("0", |_| Ok(FailingLayer)).id();
+
+    stack.step(TimeDelta::days(1)).unwrap();
+
+    let errors = stack.layer_errors(failing_id).unwrap();
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].time, stack.clock().now());
+}
+
+#[test]
+fn layers_stack_insert_remove_move_ok() {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).build();
+
+    stack.push_layer("0", |sp| Ok(Layer::new(0, sp)?));
+    stack.push_layer("3", |sp| Ok(Layer::new(3, sp)?));
+
+    stack.insert_after("0", "1", |sp| Ok(Layer::new(1, sp)?)).unwrap();
+    stack.insert_before("3", "2", |sp| Ok(Layer::new(2, sp)?)).unwrap();
+
+    stack.update().unwrap();
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [0, 1, 2, 3]);
+
+    stack.remove_layer("1").unwrap();
+    stack.move_layer("3", LayerPosition::First).unwrap();
+
+    stack.update().unwrap();
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [3, 0, 2]);
+
+    assert!(stack.get_layer("1").is_none());
+    assert_eq!(stack.get_layer("2").unwrap().name(), "2");
+
+    assert!(matches!(
+        stack.remove_layer("1").unwrap_err(),
+        LayersStackError::LayerNotFound { layer } if layer == "1"
+    ));
+
+    assert!(matches!(
+        stack.insert_after("0", "2", |sp| Ok(Layer::new(2, sp)?)).unwrap_err(),
+        LayersStackError::DuplicateLayerName { name } if name == "2"
+    ));
+}
+
+#[test]
+fn layers_stack_register_source_at_ok() {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).build();
+
+    stack.push_layer("0", |sp| Ok(Layer::new(0, sp)?));
+    stack.push_layer("1", |sp| Ok(Layer::new(1, sp)?));
+
+    stack
+        .register_source_at::<TestLayersSource>(LayerPosition::After("0".into()))
+        .unwrap();
+
+    stack.push_layer("2", |sp| Ok(Layer::new(2, sp)?));
+
+    stack.update().unwrap();
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [0, 10, 11, 1, 2]);
+}
+
+#[test]
+fn layers_stack_remove_layer_detach_ok() {
+    let (tx, rx) = mpsc::channel::<&'static str>();
+
+    let mut stack = sender_stack(tx).build();
+
+    let layer_id = stack.push_layer("0", |sp| Ok(LifecycleLayer::new(sp)?)).id();
+
+    stack.update().unwrap();
+
+    stack.remove_layer(layer_id).unwrap();
+
+    stack.update().unwrap();
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), ["attach", "update", "detach"]);
+}
+
+#[test]
+fn layers_stack_move_layer_relative_ok() {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).build();
+
+    stack.push_layer("0", |sp| Ok(Layer::new(0, sp)?));
+    stack.push_layer("1", |sp| Ok(Layer::new(1, sp)?));
+    stack.push_layer("2", |sp| Ok(Layer::new(2, sp)?));
+
+    stack.update().unwrap();
+
+    // Position relative to moved layer keeps layer in place and created
+    stack.move_layer("1", LayerPosition::Before("1".into())).unwrap();
+    stack.move_layer("1", LayerPosition::After("1".into())).unwrap();
+
+    assert!(stack.get_layer("1").unwrap().is_created());
+
+    stack.move_layer("0", LayerPosition::After("2".into())).unwrap();
+    stack.move_layer("2", LayerPosition::Last).unwrap();
+
+    stack.update().unwrap();
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [0, 1, 2, 1, 0, 2]);
+}
+
+#[test]
+fn layers_stack_groups_ok() {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).build();
+
+    stack.push_layer("0", |sp| Ok(Layer::new(0, sp)?));
+    stack.register_source_group::<TestLayersSource>("group");
+    stack.push_group("group", |stack| {
+        stack.push_layer("12", |sp| Ok(Layer::new(12, sp)?)).disable();
+    });
+    stack.push_layer("1", |sp| Ok(Layer::new(1, sp)?));
+
+    assert_eq!(
+        stack.group_layers("group").map(|layer| layer.name()).collect::<Vec<_>>(),
+        ["10", "11", "12"]
+    );
+
+    stack.update().unwrap();
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [0, 10, 11, 1]);
+
+    stack.disable_group("group").unwrap();
+
+    stack.update().unwrap();
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [0, 1]);
+    assert_eq!(stack.group_enabled("group"), Some(false));
+
+    // Layer disabled by itself stays disabled
+    stack.enable_group("group").unwrap();
+    stack.move_group("group", LayerPosition::Last).unwrap();
+
+    stack.update().unwrap();
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [0, 1, 10, 11]);
+
+    assert!(matches!(
+        stack.move_group("group", LayerPosition::Before("11".into())).unwrap_err(),
+        LayersStackError::LayerNotFound { layer } if layer == "11"
+    ));
+
+    stack.remove_group("group").unwrap();
+
+    stack.update().unwrap();
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [0, 1]);
+
+    assert!(matches!(
+        stack.disable_group("group").unwrap_err(),
+        LayersStackError::GroupNotFound { group } if group == "group"
+    ));
+}
+
+#[test]
+fn layers_stack_group_hooks_ok() {
+    let (tx, rx) = mpsc::channel::<&'static str>();
+
+    let mut stack = sender_stack(tx).build();
+
+    stack.push_group("group", |stack| {
+        stack.push_layer("0", |sp| Ok(LifecycleLayer::new(sp)?));
+    });
+
+    stack.update().unwrap();
+
+    stack.disable_group("group").unwrap();
+
+    // Layer enabled inside disabled group, no hooks called
+    let layer_id = stack.get_layer("0").unwrap().id();
+
+    stack.disable(layer_id);
+    stack.enable(layer_id);
+
+    stack.enable_group("group").unwrap();
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), ["attach", "update", "disable", "enable"]);
+}
+
+#[test]
+fn layers_stack_nested_stack_ok() {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).build();
+
+    stack.push_layer("0", |sp| Ok(Layer::new(0, sp)?));
+    stack.push_layer("child", |sp| {
+        let mut child = sp.resolve::<LayersStack>()?;
+
+        child.push_layer("10", |sp| Ok(DepLayer::new(10, [], sp)?));
+        child.push_layer("11", |sp| Ok(Layer::new(11, sp)?));
+
+        Ok(child)
+    });
+    stack.push_layer("1", |sp| Ok(Layer::new(1, sp)?));
+
+    stack.update().unwrap();
+
+    // Child tasks awaited inside child layer update, child tasks run in parallel
+    let res = rx.try_iter().collect::<Vec<_>>();
+
+    assert_eq!(res.len(), 4);
+    assert_eq!((res[0], res[3]), (0, 1));
+    assert!(res[1..3].contains(&10) && res[1..3].contains(&11));
+
+    let child_id = stack.get_layer("child").unwrap().id();
+    stack.disable(child_id);
+
+    stack.update().unwrap();
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [0, 1]);
+}
+
+#[test]
+fn layers_stack_manual_clock_step_ok() {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).build();
+
+    stack.clock().set_manual();
+
+    stack.push_layer("dt", |sp| Ok(DtLayer::new(sp)?));
+    stack
+        .push_layer("fixed", |sp| Ok(DtLayer::new(sp)?))
+        .with_fixed_timestep(FixedTimestep::new(TimeDelta::milliseconds(10)));
+
+    // First frame has zero dt, manual clock time not changed between updates
+    stack.update().unwrap();
+    stack.update().unwrap();
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [0, 0]);
+
+    stack.clock().advance(TimeDelta::milliseconds(25));
+    stack.update().unwrap();
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [25, 10, 10]);
+
+    let start = stack.clock().now();
+
+    stack.run_frames(3, TimeDelta::milliseconds(5)).unwrap();
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [5, 10, 5, 5, 10]);
+    assert_eq!(stack.clock().now() - start, TimeDelta::milliseconds(15));
+}
+
+#[test]
+fn layers_stack_step_real_clock_ok() {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).build();
+
+    stack.clock().set_real();
+
+    stack.push_layer("dt", |sp| Ok(DtLayer::new(sp)?));
+
+    let before = Utc::now();
+
+    // Real clock not advanced, layers still get requested dt
+    stack.step(TimeDelta::seconds(2)).unwrap();
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [2000]);
+    assert!(stack.clock().now() - before < TimeDelta::seconds(2));
+}
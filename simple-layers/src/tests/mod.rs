@@ -1 +1,17 @@
+pub mod background;
+pub mod condition;
+pub mod config;
+pub mod events;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod inspector;
 pub mod layer;
+pub mod pipeline;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod plugin;
+pub mod profiler;
+pub mod scheduler;
+pub mod stage;
+pub mod stats;
+pub mod sync;
+pub mod testing;
+pub mod time;
@@ -0,0 +1,206 @@
+use std::{
+    sync::mpsc::{self, Sender as SyncSender},
+    time::{Duration, Instant},
+};
+
+use xdi::{ServiceProvider, types::error::ServiceBuildResult};
+
+use crate::{
+    config::{LayersRegistry, LayersStackConfig},
+    layer::ILayer,
+    pipeline::{ILayersPipelineDependencies, Snapshot},
+    scheduler::LayerScheduler,
+    stage::Stage,
+};
+
+use super::{scheduler::FnLayer, testing::sender_stack};
+
+/// Send and extract `frame * 10` on update
+#[derive(Debug)]
+pub struct SimLayer {
+    frame: i32,
+    snapshot: Snapshot<i32>,
+    sender: SyncSender<i32>,
+}
+
+impl SimLayer {
+    pub fn new(sp: ServiceProvider) -> ServiceBuildResult<Self> {
+        Ok(Self {
+            frame: 0,
+            snapshot: sp.resolve()?,
+            sender: sp.resolve()?,
+        })
+    }
+}
+
+impl ILayer for SimLayer {
+    fn on_update(&mut self, _dt: &chrono::TimeDelta, _scheduler: &mut LayerScheduler) -> anyhow::Result<()> {
+        self.frame += 1;
+
+        self.sender.send(self.frame * 10).unwrap();
+        self.snapshot.extract(self.frame * 10);
+
+        Ok(())
+    }
+}
+
+/// Send extracted value + 1 from slow task
+#[derive(Debug)]
+pub struct RenderLayer {
+    snapshot: Snapshot<i32>,
+    sender: SyncSender<i32>,
+}
+
+impl RenderLayer {
+    pub fn new(sp: ServiceProvider) -> ServiceBuildResult<Self> {
+        Ok(Self {
+            snapshot: sp.resolve()?,
+            sender: sp.resolve()?,
+        })
+    }
+}
+
+impl ILayer for RenderLayer {
+    fn on_update(&mut self, _dt: &chrono::TimeDelta, scheduler: &mut LayerScheduler) -> anyhow::Result<()> {
+        let extracted = self.snapshot.latest().unwrap();
+        let sender = self.sender.clone();
+
+        scheduler.schedule(
+            async move {
+                std::thread::sleep(Duration::from_millis(30));
+                sender.send(*extracted.value + 1).unwrap();
+            },
+            (),
+        );
+
+        Ok(())
+    }
+}
+
+#[test]
+fn pipeline_disabled_by_default_ok() {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx)
+        .register(|builder| builder.register_snapshot::<i32>())
+        .build();
+
+    stack.push_layer("render", |sp| Ok(RenderLayer::new(sp)?)).with_stage(Stage::Render);
+    stack.push_layer("sim", |sp| Ok(SimLayer::new(sp)?));
+
+    stack.update().unwrap();
+    stack.update().unwrap();
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [10, 11, 20, 21]);
+    assert_eq!(stack.scheduler().frames_in_flight(), 0);
+}
+
+#[test]
+fn pipeline_render_overlaps_next_frame_ok() {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx)
+        .register(|builder| builder.register_snapshot::<i32>())
+        .build();
+
+    stack.set_pipeline_depth(1);
+
+    stack.push_layer("render", |sp| Ok(RenderLayer::new(sp)?)).with_stage(Stage::Render);
+    stack.push_layer("sim", |sp| Ok(SimLayer::new(sp)?));
+
+    stack.update().unwrap();
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [10]);
+    assert_eq!(stack.scheduler().frames_in_flight(), 1);
+
+    // Second frame simulated before first frame rendered, render waits own frame snapshot
+    stack.update().unwrap();
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [20, 11]);
+    assert_eq!(stack.scheduler().frames_in_flight(), 1);
+
+    stack.shutdown();
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [21]);
+    assert_eq!(stack.scheduler().frames_in_flight(), 0);
+}
+
+#[test]
+fn pipeline_wait_inside_frame_ok() {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx)
+        .register(|builder| builder.register_snapshot::<i32>())
+        .build();
+
+    stack.set_pipeline_depth(1);
+
+    stack.push_layer("render", |sp| Ok(RenderLayer::new(sp)?)).with_stage(Stage::Render);
+    stack
+        .push_layer("commands", |_| {
+            Ok(FnLayer(|scheduler: &mut LayerScheduler| {
+                scheduler.wait_all_blocking().unwrap();
+            }))
+        })
+        .with_stage(Stage::Render);
+    stack.push_layer("sim", |sp| Ok(SimLayer::new(sp)?));
+
+    stack.update().unwrap();
+
+    // Wait inside frame completes render tasks scheduled before it, nothing left in flight
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [10, 11]);
+    assert_eq!(stack.scheduler().frames_in_flight(), 0);
+}
+
+#[test]
+fn pipeline_frame_deadline_ok() {
+    let (tx, _rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).build();
+
+    stack.set_pipeline_depth(1);
+    stack.scheduler_mut().set_frame_deadline(Some(Duration::from_millis(20)));
+
+    stack
+        .push_layer("render", |_| {
+            Ok(FnLayer(|scheduler: &mut LayerScheduler| {
+                scheduler.schedule(async { tokio::time::sleep(Duration::from_secs(10)).await }, ());
+            }))
+        })
+        .with_stage(Stage::Render);
+
+    let start = Instant::now();
+
+    stack.update().unwrap();
+
+    assert!(stack.scheduler().straggler_report().is_none());
+
+    // Oldest frame render task missed second frame deadline
+    stack.update().unwrap();
+
+    let report = stack.scheduler().straggler_report().unwrap();
+
+    assert_eq!(report.tasks.len(), 1);
+    assert_eq!(report.tasks[0].layer_name, "render");
+
+    // Cancelled render tasks not waited till completion
+    stack.shutdown();
+
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert_eq!(stack.scheduler().frames_in_flight(), 0);
+}
+
+#[test]
+fn pipeline_depth_config_ok() {
+    let (tx, _rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx)
+        .register(|builder| builder.register_snapshot::<i32>())
+        .build();
+
+    let config = LayersStackConfig::from_toml("pipeline_depth = 2").unwrap();
+
+    stack.apply_config(&LayersRegistry::new(), &config, None).unwrap();
+
+    assert_eq!(stack.scheduler().pipeline_depth(), 2);
+}
@@ -0,0 +1,258 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+    sync::mpsc,
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    layer::LayerPosition,
+    plugin::{PLUGIN_ABI_VERSION, PluginLoader, PluginRegistrar},
+    types::error::{LayersStackError, PluginError},
+};
+
+use super::{layer::LifecycleLayer, testing::sender_stack};
+
+fn register_plugin(registrar: &mut PluginRegistrar) {
+    registrar.insert_after("0", "plugin", |sp| Ok(LifecycleLayer::new(sp)?));
+    registrar.with_group("plugins");
+}
+
+fn register_services_plugin(registrar: &mut PluginRegistrar) {
+    register_plugin(registrar);
+    registrar.register_services(|_| {});
+}
+
+/// Build `cdylib` exporting entry point `symbol` which return abi version `version`
+fn build_fixture(dir: &Path, name: &str, symbol: &str, version: u32) -> PathBuf {
+    let source = dir.join(format!("{name}.rs"));
+
+    std::fs::write(
+        &source,
+        format!(
+            "#[unsafe(no_mangle)]\n\
+             pub unsafe extern \"C\" fn {symbol}(_abi_version: u32, _registrar: *mut u8) -> u32 {{ {version} }}\n"
+        ),
+    )
+    .unwrap();
+
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+
+    let status = Command::new(rustc)
+        .args(["--edition", "2024", "--crate-type", "cdylib", "--crate-name", name, "--out-dir"])
+        .arg(dir)
+        .arg(&source)
+        .status()
+        .unwrap();
+
+    assert!(status.success(), "Fixture [{name}] build failed");
+
+    dir.join(format!(
+        "{}{name}{}",
+        std::env::consts::DLL_PREFIX,
+        std::env::consts::DLL_SUFFIX
+    ))
+}
+
+/// Loader copy of plugin library
+fn copy_path(path: &Path, generation: u32) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "{}-{generation}-{}",
+        std::process::id(),
+        path.file_name().unwrap().to_string_lossy()
+    ))
+}
+
+#[test]
+fn plugin_reload_ok() {
+    let (tx, rx) = mpsc::channel::<&'static str>();
+
+    let mut stack = sender_stack(tx).build();
+
+    stack.push_layer("0", |sp| Ok(LifecycleLayer::new(sp)?)).disable();
+    stack.push_layer("1", |sp| Ok(LifecycleLayer::new(sp)?)).disable();
+
+    let mut loader = PluginLoader::new();
+
+    loader.load_static("test_plugin", register_plugin).unwrap();
+    loader.attach(&mut stack).unwrap();
+
+    stack.update().unwrap();
+
+    // Moved layer position preserved on reload
+    stack.move_layer("plugin", LayerPosition::Last).unwrap();
+
+    loader.reload("test_plugin", &mut stack).unwrap();
+
+    stack.update().unwrap();
+
+    assert_eq!(
+        stack.layers().map(|layer| layer.name()).collect::<Vec<_>>(),
+        ["0", "1", "plugin"]
+    );
+    assert_eq!(stack.get_layer("plugin").unwrap().group(), Some("plugins"));
+
+    assert_eq!(
+        rx.try_iter().collect::<Vec<_>>(),
+        ["attach", "update", "detach", "attach", "update"]
+    );
+
+    loader.unload("test_plugin", &mut stack).unwrap();
+
+    assert!(stack.get_layer("plugin").is_none());
+    assert_eq!(loader.plugins().count(), 0);
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), ["detach"]);
+}
+
+#[test]
+fn plugin_reload_services_err() {
+    let (tx, rx) = mpsc::channel::<&'static str>();
+
+    let mut stack = sender_stack(tx).build();
+
+    stack.push_layer("0", |sp| Ok(LifecycleLayer::new(sp)?)).disable();
+
+    let mut loader = PluginLoader::new();
+
+    loader.load_static("test_plugin", register_services_plugin).unwrap();
+    loader.attach(&mut stack).unwrap();
+
+    stack.update().unwrap();
+
+    assert!(matches!(
+        loader.reload("test_plugin", &mut stack).unwrap_err(),
+        PluginError::ServicesOnReload { plugin } if plugin == "test_plugin"
+    ));
+
+    // Old layer kept running
+    stack.update().unwrap();
+
+    assert!(stack.get_layer("plugin").unwrap().is_created());
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), ["attach", "update", "update"]);
+}
+
+#[test]
+fn plugin_load_err() {
+    let mut loader = PluginLoader::new();
+
+    assert!(matches!(
+        loader.load("missing/libplugin.so").unwrap_err(),
+        PluginError::Load { path, .. } if path == "missing/libplugin.so"
+    ));
+
+    let (tx, _rx) = mpsc::channel::<&'static str>();
+
+    let mut stack = sender_stack(tx).build();
+
+    assert!(matches!(
+        loader.reload("missing", &mut stack).unwrap_err(),
+        PluginError::NotFound { plugin } if plugin == "missing"
+    ));
+
+    // Plugin layer position target not found
+    loader.load_static("test_plugin", register_plugin).unwrap();
+
+    assert!(matches!(
+        loader.attach(&mut stack).unwrap_err(),
+        PluginError::Stack(LayersStackError::LayerNotFound { layer }) if layer == "0"
+    ));
+}
+
+#[test]
+fn plugin_library_ok() {
+    let dir = std::env::temp_dir().join(format!("simple-layers-plugin-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let path = build_fixture(&dir, "fixture_ok", "simple_layers_plugin_entry", PLUGIN_ABI_VERSION);
+
+    let (tx, _rx) = mpsc::channel::<&'static str>();
+
+    let mut stack = sender_stack(tx).build();
+
+    let mut loader = PluginLoader::new();
+
+    let name = loader.load(&path).unwrap().to_string();
+    loader.attach(&mut stack).unwrap();
+
+    assert!(copy_path(&path, 1).exists());
+    assert!(!loader.poll_reload(&mut stack).unwrap());
+
+    // Rewritten by build
+    std::fs::File::options()
+        .write(true)
+        .open(&path)
+        .unwrap()
+        .set_modified(SystemTime::now() + Duration::from_secs(10))
+        .unwrap();
+
+    assert!(loader.poll_reload(&mut stack).unwrap());
+    assert!(!copy_path(&path, 1).exists());
+    assert!(copy_path(&path, 2).exists());
+
+    // Broken build rejected, loaded library kept
+    std::fs::write(&path, "not a library").unwrap();
+
+    std::fs::File::options()
+        .write(true)
+        .open(&path)
+        .unwrap()
+        .set_modified(SystemTime::now() + Duration::from_secs(20))
+        .unwrap();
+
+    let PluginError::Reload { errors } = loader.poll_reload(&mut stack).unwrap_err() else {
+        panic!("Expected reload error");
+    };
+
+    assert!(matches!(errors.as_slice(), [PluginError::Load { .. }]));
+    assert!(copy_path(&path, 2).exists());
+    assert!(!copy_path(&path, 3).exists());
+
+    // Failed file not reloaded until changed again
+    assert!(!loader.poll_reload(&mut stack).unwrap());
+
+    loader.unload(&name, &mut stack).unwrap();
+
+    assert!(!copy_path(&path, 2).exists());
+
+    _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn plugin_library_err() {
+    let dir = std::env::temp_dir().join(format!("simple-layers-plugin-err-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let mut loader = PluginLoader::new();
+
+    let mismatch = build_fixture(&dir, "fixture_mismatch", "simple_layers_plugin_entry", PLUGIN_ABI_VERSION + 1);
+
+    assert!(matches!(
+        loader.load(&mismatch).unwrap_err(),
+        PluginError::AbiMismatch { expected, found, .. } if expected == PLUGIN_ABI_VERSION && found == PLUGIN_ABI_VERSION + 1
+    ));
+
+    let missing = build_fixture(&dir, "fixture_missing", "other_entry", PLUGIN_ABI_VERSION);
+
+    assert!(matches!(
+        loader.load(&missing).unwrap_err(),
+        PluginError::MissingEntry { .. }
+    ));
+
+    let invalid = dir.join(format!(
+        "{}fixture_invalid{}",
+        std::env::consts::DLL_PREFIX,
+        std::env::consts::DLL_SUFFIX
+    ));
+    std::fs::write(&invalid, "not a library").unwrap();
+
+    assert!(matches!(loader.load(&invalid).unwrap_err(), PluginError::Load { .. }));
+
+    // Copies of rejected libraries removed
+    for path in [&mismatch, &missing, &invalid] {
+        assert!(!copy_path(path, 1).exists());
+    }
+
+    assert_eq!(loader.plugins().count(), 0);
+
+    _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,92 @@
+use std::sync::mpsc;
+use serde_json::{Value, json};
+
+use crate::profiler::chrome_trace;
+
+use super::{scheduler::DepLayer, testing::sender_stack};
+
+#[test]
+fn profiler_disabled_by_default_ok() {
+    let (tx, _rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).with_profiler(false).build();
+
+    stack.push_layer("0", |sp| Ok(DepLayer::new(0, [], sp)?));
+
+    stack.update().unwrap();
+
+    assert!(stack.profiler().history().is_empty());
+}
+
+#[test]
+fn profiler_records_frames_ok() {
+    let (tx, _rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).build();
+
+    stack.profiler().set_history_len(2);
+
+    stack.push_layer("0", |sp| Ok(DepLayer::new(0, ["1"], sp)?));
+    stack.push_layer("1", |sp| Ok(DepLayer::new(1, [], sp)?));
+    stack.push_layer("2", |sp| Ok(DepLayer::new(2, [], sp)?)).disable();
+
+    for _ in 0..3 {
+        stack.update().unwrap();
+    }
+
+    let history = stack.profiler().history();
+
+    assert_eq!(history.iter().map(|frame| frame.index).collect::<Vec<_>>(), [2, 3]);
+
+    let frame = &history[1];
+
+    assert_eq!(
+        frame.layers.iter().map(|layer| layer.layer_name.as_str()).collect::<Vec<_>>(),
+        ["0", "1"]
+    );
+    assert_eq!(frame.waits.len(), 1);
+
+    let mut tasks = frame.tasks.iter().map(|task| task.layer_name.as_str()).collect::<Vec<_>>();
+    tasks.sort();
+
+    assert_eq!(tasks, ["0", "1"]);
+
+    for task in &frame.tasks {
+        assert!(task.queued <= task.span.start);
+        assert!(task.span.start <= task.span.end);
+        assert!(frame.start <= task.queued && task.span.end <= frame.end);
+    }
+}
+
+#[test]
+fn profiler_chrome_trace_ok() {
+    let (tx, _rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).build();
+
+    stack.push_layer("render \"main\"", |sp| Ok(DepLayer::new(0, [], sp)?));
+
+    stack.update().unwrap();
+
+    let trace = serde_json::from_str::<Value>(&stack.profiler().to_chrome_trace()).unwrap();
+    let events = trace["traceEvents"].as_array().unwrap();
+
+    let event = |name: &str, category: &str| {
+        events
+            .iter()
+            .find(|event| event["name"] == name && event["cat"] == category)
+            .unwrap_or_else(|| panic!("Trace event [{name}] of [{category}] not found"))
+    };
+
+    assert_eq!(event("render \"main\"", "layer")["ph"], "X");
+    assert!(event("render \"main\"", "task")["args"]["queued_us"].is_u64());
+    assert_eq!(event("wait_all_blocking", "wait")["tid"], 0);
+    assert!(events.iter().any(|event| event["name"] == "thread_name"));
+
+    assert_eq!(
+        chrome_trace(&[]),
+        json!({
+            "traceEvents": [{ "name": "thread_name", "ph": "M", "pid": 1, "tid": 0, "args": { "name": "layers stack" } }]
+        })
+    );
+}
@@ -0,0 +1,796 @@
+use std::{
+    sync::mpsc::{self, Sender as SyncSender},
+    time::{Duration, Instant},
+};
+use xdi::{ServiceProvider, types::error::ServiceBuildResult};
+
+use crate::{
+    layer::{ILayer, LayerCtx, LayerErrorPolicy},
+    scheduler::LayerScheduler,
+    testing::TestStack,
+    types::{
+        error::{LayerErrorStage, LayersStackError, ScheduleDiagnostic, TaskErrorKind},
+        sync::CancellationToken,
+        task::{TaskHandle, TaskOptions},
+    },
+};
+
+use super::testing::sender_stack;
+
+#[derive(Debug)]
+pub struct DepLayer<const N: usize> {
+    data: i32,
+    deps: [&'static str; N],
+    sender: SyncSender<i32>,
+}
+
+impl<const N: usize> DepLayer<N> {
+    pub fn new(data: i32, deps: [&'static str; N], sp: ServiceProvider) -> ServiceBuildResult<Self> {
+        Ok(Self {
+            data,
+            deps,
+            sender: sp.resolve()?,
+        })
+    }
+}
+
+impl<const N: usize> ILayer for DepLayer<N> {
+    fn on_update(&mut self, _dt: &chrono::TimeDelta, scheduler: &mut LayerScheduler) -> anyhow::Result<()> {
+        let sender = self.sender.clone();
+        let data = self.data;
+
+        scheduler.schedule(
+            async move {
+                sender.send(data).unwrap();
+            },
+            self.deps,
+        );
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct PanicLayer;
+
+impl ILayer for PanicLayer {
+    fn on_update(&mut self, _dt: &chrono::TimeDelta, scheduler: &mut LayerScheduler) -> anyhow::Result<()> {
+        scheduler.schedule(
+            async move {
+                panic!("task panic");
+            },
+            (),
+        );
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct ResultLayer {
+    frame: i32,
+    handle: Option<TaskHandle<i32>>,
+    sender: SyncSender<i32>,
+}
+
+impl ResultLayer {
+    pub fn new(sp: ServiceProvider) -> ServiceBuildResult<Self> {
+        Ok(Self {
+            frame: 0,
+            handle: None,
+            sender: sp.resolve()?,
+        })
+    }
+}
+
+impl ILayer for ResultLayer {
+    fn on_update(&mut self, _dt: &chrono::TimeDelta, scheduler: &mut LayerScheduler) -> anyhow::Result<()> {
+        // Previous frame task result
+        if let Some(mut handle) = self.handle.take() {
+            self.sender.send(handle.try_take().unwrap()?).unwrap();
+        }
+
+        self.frame += 1;
+
+        let frame = self.frame;
+
+        let handle = scheduler.schedule_with_result(async move { frame * 10 }, ());
+
+        let sender = self.sender.clone();
+
+        scheduler.schedule(
+            async move {
+                sender.send(handle.join().await.unwrap() + 1).unwrap();
+            },
+            (),
+        );
+
+        self.handle = Some(scheduler.schedule_with_result(async move { frame * 100 }, ()));
+
+        Ok(())
+    }
+}
+
+/// Shared resources for access declaration
+pub struct RenderTarget;
+pub struct Materials;
+
+#[derive(Debug)]
+pub struct AccessLayer<const N: usize> {
+    data: i32,
+    delay: Duration,
+    deps: [&'static str; N],
+    options: TaskOptions,
+    sender: SyncSender<i32>,
+}
+
+impl<const N: usize> AccessLayer<N> {
+    pub fn new(
+        data: i32,
+        delay: Duration,
+        deps: [&'static str; N],
+        options: TaskOptions,
+        sp: ServiceProvider,
+    ) -> ServiceBuildResult<Self> {
+        Ok(Self {
+            data,
+            delay,
+            deps,
+            options,
+            sender: sp.resolve()?,
+        })
+    }
+}
+
+impl<const N: usize> ILayer for AccessLayer<N> {
+    fn on_update(&mut self, _dt: &chrono::TimeDelta, scheduler: &mut LayerScheduler) -> anyhow::Result<()> {
+        let sender = self.sender.clone();
+        let (data, delay) = (self.data, self.delay);
+
+        scheduler.schedule_with_options(
+            async move {
+                std::thread::sleep(delay);
+                sender.send(data).unwrap();
+            },
+            self.deps,
+            self.options.clone(),
+        );
+
+        Ok(())
+    }
+}
+
+pub struct FnLayer<F>(pub F);
+
+impl<F> std::fmt::Debug for FnLayer<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("FnLayer").finish()
+    }
+}
+
+impl<F: FnMut(&mut LayerScheduler) + 'static> ILayer for FnLayer<F> {
+    fn on_update(&mut self, _dt: &chrono::TimeDelta, scheduler: &mut LayerScheduler) -> anyhow::Result<()> {
+        (self.0)(scheduler);
+
+        Ok(())
+    }
+}
+
+#[test]
+fn scheduler_forward_dependency_ok() {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).with_worker_threads(4).build();
+
+    stack.push_layer("0", |sp| Ok(DepLayer::new(0, ["1"], sp)?));
+    stack.push_layer("1", |sp| Ok(DepLayer::new(1, ["2"], sp)?));
+    stack.push_layer("2", |sp| Ok(DepLayer::new(2, [], sp)?));
+
+    stack.update().unwrap();
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [2, 1, 0]);
+    assert!(stack.scheduler().diagnostics().is_empty());
+}
+
+#[test]
+fn scheduler_backward_dependency_overlaps_update_ok() {
+    let mut stack = TestStack::builder().build();
+
+    stack.push_layer("0", |_| {
+        Ok(FnLayer(|scheduler: &mut LayerScheduler| {
+            scheduler.schedule(async {}, ());
+        }))
+    });
+
+    stack.push_layer("1", |_| {
+        Ok(FnLayer(|scheduler: &mut LayerScheduler| {
+            scheduler.schedule(async {}, ["0"]);
+        }))
+    });
+
+    stack.push_layer("2", |_| {
+        Ok(FnLayer(|_: &mut LayerScheduler| {
+            std::thread::sleep(Duration::from_millis(50));
+        }))
+    });
+
+    stack.frame(chrono::TimeDelta::milliseconds(10));
+
+    let frame = stack.last_frame();
+
+    let task_end = frame.tasks.iter().find(|task| task.layer_name == "1").unwrap().span.end;
+    let update_end = frame.layers.iter().find(|layer| layer.layer_name == "2").unwrap().span.end;
+
+    // Dependency on updated layer resolved on schedule, task not waiting next layers update
+    assert!(task_end < update_end);
+    assert!(stack.scheduler().diagnostics().is_empty());
+}
+
+#[test]
+fn scheduler_unknown_and_disabled_dependency_diagnostics_ok() {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).with_worker_threads(4).build();
+
+    stack.push_layer("0", |sp| Ok(DepLayer::new(0, ["render_pass_strat"], sp)?));
+    stack.push_layer("1", |sp| Ok(DepLayer::new(1, [], sp)?)).disable();
+    stack.push_layer("2", |sp| Ok(DepLayer::new(2, ["1"], sp)?));
+
+    stack.update().unwrap();
+
+    let mut res = rx.try_iter().collect::<Vec<_>>();
+    res.sort();
+
+    assert_eq!(res, [0, 2]);
+
+    assert_eq!(
+        stack.scheduler().diagnostics(),
+        [
+            ScheduleDiagnostic::UnknownDependency {
+                layer: "0".to_string(),
+                dependency: "render_pass_strat".to_string(),
+            },
+            ScheduleDiagnostic::DisabledDependency {
+                layer: "2".to_string(),
+                dependency: "1".to_string(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn scheduler_cycle_diagnostic_ok() {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).with_worker_threads(4).build();
+
+    stack.push_layer("0", |sp| Ok(DepLayer::new(0, ["1"], sp)?));
+    stack.push_layer("1", |sp| Ok(DepLayer::new(1, ["0"], sp)?));
+
+    stack.update().unwrap();
+
+    assert_eq!(rx.try_iter().count(), 2);
+
+    let [ScheduleDiagnostic::Cycle { layers }] = stack.scheduler().diagnostics() else {
+        panic!("Expected cycle diagnostic");
+    };
+
+    assert_eq!(layers.len(), 2);
+}
+
+#[test]
+fn scheduler_strict_mode_err() {
+    let (tx, _rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).with_worker_threads(4).build();
+
+    stack.scheduler_mut().set_strict(true);
+
+    stack.push_layer("0", |sp| Ok(DepLayer::new(0, ["unknown"], sp)?));
+
+    let LayersStackError::Schedule(err) = stack.update().unwrap_err() else {
+        panic!("Expected schedule error");
+    };
+
+    assert_eq!(err.diagnostics.len(), 1);
+}
+
+#[test]
+fn scheduler_task_result_ok() {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).with_worker_threads(4).build();
+
+    stack.push_layer("0", |sp| Ok(ResultLayer::new(sp)?));
+
+    stack.update().unwrap();
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [11]);
+
+    stack.update().unwrap();
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [100, 21]);
+}
+
+#[test]
+fn scheduler_task_panic_reported_ok() {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).with_worker_threads(4).build();
+
+    let panic_id = stack.push_layer("panic", |_| Ok(PanicLayer)).id();
+    stack.push_layer("0", |sp| Ok(DepLayer::new(0, ["panic"], sp)?));
+
+    stack.update().unwrap();
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [0]);
+
+    let errors = stack.layer_errors(panic_id).unwrap();
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].stage, LayerErrorStage::Task);
+    assert!(errors[0].error.to_string().contains("task panic"));
+}
+
+#[test]
+fn scheduler_task_panic_abort_err() {
+    let (tx, _rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).with_worker_threads(4).build();
+
+    stack
+        .push_layer("panic", |_| Ok(PanicLayer))
+        .with_error_policy(LayerErrorPolicy::Abort);
+
+    let LayersStackError::Layer(err) = stack.update().unwrap_err() else {
+        panic!("Expected layer error");
+    };
+
+    assert_eq!(err.name, "panic");
+    assert_eq!(err.stage, LayerErrorStage::Task);
+}
+
+#[test]
+fn scheduler_task_timeout_ok() {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).with_worker_threads(4).build();
+
+    let layer_id = stack
+        .push_layer("0", move |sp| {
+            let sender = sp.resolve::<SyncSender<i32>>()?;
+
+            Ok(FnLayer(move |scheduler: &mut LayerScheduler| {
+                let handle = scheduler.schedule_with_options(
+                    async { tokio::time::sleep(Duration::from_secs(10)).await },
+                    (),
+                    TaskOptions::new().with_timeout(Duration::from_millis(10)),
+                );
+
+                let sender = sender.clone();
+
+                scheduler.schedule(
+                    async move {
+                        let err = handle.join().await.unwrap_err();
+                        sender.send((err.kind == TaskErrorKind::TimedOut) as i32).unwrap();
+                    },
+                    (),
+                );
+            }))
+        })
+        .id();
+
+    stack.update().unwrap();
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [1]);
+
+    let errors = stack.layer_errors(layer_id).unwrap();
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].stage, LayerErrorStage::Task);
+}
+
+#[test]
+fn scheduler_task_cancellation_ok() {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).with_worker_threads(4).build();
+
+    let layer_id = stack
+        .push_layer("0", move |sp| {
+            let sender = sp.resolve::<SyncSender<i32>>()?;
+
+            Ok(FnLayer(move |scheduler: &mut LayerScheduler| {
+                let cancellation = CancellationToken::new();
+
+                let handle = scheduler.schedule_with_options(
+                    std::future::pending::<()>(),
+                    (),
+                    TaskOptions::new().with_cancellation(cancellation.clone()),
+                );
+
+                let sender = sender.clone();
+
+                scheduler.schedule(
+                    async move {
+                        cancellation.cancel();
+
+                        let err = handle.join().await.unwrap_err();
+                        sender.send((err.kind == TaskErrorKind::Cancelled) as i32).unwrap();
+                    },
+                    (),
+                );
+            }))
+        })
+        .id();
+
+    stack.update().unwrap();
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [1]);
+
+    // Cancellation is not a layer error
+    assert!(stack.layer_errors(layer_id).unwrap().is_empty());
+}
+
+#[test]
+fn scheduler_frame_deadline_stragglers_ok() {
+    let (tx, _rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).with_worker_threads(4).build();
+
+    stack.scheduler_mut().set_frame_deadline(Some(Duration::from_millis(50)));
+
+    stack.push_layer("stuck", |_| {
+        Ok(FnLayer(|scheduler: &mut LayerScheduler| {
+            scheduler.schedule(async { tokio::time::sleep(Duration::from_secs(10)).await }, ());
+        }))
+    });
+
+    stack.push_layer("fast", |_| {
+        Ok(FnLayer(|scheduler: &mut LayerScheduler| {
+            scheduler.schedule(async {}, ());
+        }))
+    });
+
+    let start = Instant::now();
+
+    stack.update().unwrap();
+
+    assert!(start.elapsed() < Duration::from_secs(5));
+
+    let report = stack.scheduler().straggler_report().unwrap();
+
+    assert_eq!(report.tasks.len(), 1);
+    assert_eq!(report.tasks[0].layer_name, "stuck");
+    assert!(report.tasks[0].started);
+}
+
+#[test]
+fn scheduler_frame_deadline_main_thread_ok() {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).build();
+
+    stack.scheduler_mut().set_frame_deadline(Some(Duration::from_millis(20)));
+
+    stack.push_layer("slow", |_| {
+        Ok(FnLayer(|scheduler: &mut LayerScheduler| {
+            scheduler.schedule(async { tokio::time::sleep(Duration::from_secs(10)).await }, ());
+        }))
+    });
+
+    stack.push_layer("main", |sp| {
+        let sender = sp.resolve::<SyncSender<i32>>()?;
+
+        Ok(FnLayer(move |scheduler: &mut LayerScheduler| {
+            let sender = sender.clone();
+
+            scheduler.schedule_main_thread(
+                async move {
+                    sender.send(1).unwrap();
+                },
+                ["slow"],
+                TaskOptions::new(),
+            );
+        }))
+    });
+
+    stack.push_layer("main_slow", |_| {
+        Ok(FnLayer(|scheduler: &mut LayerScheduler| {
+            scheduler.schedule_main_thread(
+                async { tokio::time::sleep(Duration::from_secs(10)).await },
+                (),
+                TaskOptions::new(),
+            );
+        }))
+    });
+
+    let start = Instant::now();
+
+    stack.update().unwrap();
+
+    // Main thread tasks not drained past deadline, waiting task cancelled with slow layer
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert_eq!(rx.try_iter().count(), 0);
+
+    let report = stack.scheduler().straggler_report().unwrap();
+
+    let mut layers = report.tasks.iter().map(|task| task.layer_name.as_str()).collect::<Vec<_>>();
+    layers.sort();
+
+    assert_eq!(layers, ["main", "main_slow", "slow"]);
+}
+
+#[test]
+fn scheduler_resource_access_ordering_ok() {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).with_worker_threads(4).build();
+
+    let delay = Duration::from_millis(30);
+
+    stack.push_layer("0", move |sp| Ok(AccessLayer::new(0, delay, [], TaskOptions::new().writes::<RenderTarget>(), sp)?));
+    stack.push_layer("1", move |sp| Ok(AccessLayer::new(1, delay, [], TaskOptions::new().reads::<RenderTarget>(), sp)?));
+    stack.push_layer("2", |sp| Ok(AccessLayer::new(2, Duration::ZERO, [], TaskOptions::new().reads::<RenderTarget>(), sp)?));
+    stack.push_layer("3", |sp| Ok(AccessLayer::new(3, Duration::ZERO, [], TaskOptions::new().writes::<Materials>(), sp)?));
+
+    stack.update().unwrap();
+
+    // Not conflicting tasks run in parallel, readers wait earlier writer
+    let res = rx.try_iter().collect::<Vec<_>>();
+
+    assert_eq!(res[0], 3);
+    assert_eq!(res[1], 0);
+
+    let conflicts = stack
+        .scheduler()
+        .diagnostics()
+        .iter()
+        .map(|diagnostic| {
+            let ScheduleDiagnostic::AccessConflict { resource, first, second } = diagnostic else {
+                panic!("Expected access conflict diagnostic");
+            };
+
+            assert!(resource.ends_with("RenderTarget"));
+
+            (first.as_str(), second.as_str())
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(conflicts, [("0", "1"), ("0", "2")]);
+}
+
+#[test]
+fn scheduler_resource_access_ordered_by_dependency_ok() {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).with_worker_threads(4).build();
+
+    let delay = Duration::from_millis(30);
+    let options = || TaskOptions::new().reads::<Materials>().writes::<RenderTarget>();
+
+    stack.push_layer("0", move |sp| Ok(AccessLayer::new(0, Duration::ZERO, ["1"], options(), sp)?));
+    stack.push_layer("1", move |sp| Ok(AccessLayer::new(1, delay, [], options(), sp)?));
+    stack.push_layer("2", move |sp| Ok(AccessLayer::new(2, Duration::ZERO, ["0"], options(), sp)?));
+
+    stack.update().unwrap();
+
+    // Dependencies order conflicting tasks against schedule order
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [1, 0, 2]);
+    assert!(stack.scheduler().diagnostics().is_empty());
+}
+
+#[test]
+fn scheduler_task_layer_ctx_ok() {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).build();
+
+    for name in ["1", "2", "3"] {
+        stack.push_layer(name, move |sp| {
+            let sender = sp.resolve::<SyncSender<i32>>()?;
+
+            Ok(FnLayer(move |scheduler: &mut LayerScheduler| {
+                let sp = sp.clone();
+                let sender = sender.clone();
+
+                scheduler.schedule(
+                    async move {
+                        let task = LayerCtx::task().unwrap();
+
+                        // Worker thread context not changed by layers update
+                        let lc = sp.resolve::<LayerCtx>().unwrap();
+
+                        let data = match lc.name() == task.name && lc.id() == task.id {
+                            true => task.name.parse::<i32>().unwrap() * 10 + task.frame as i32,
+                            false => -1,
+                        };
+
+                        sender.send(data).unwrap();
+                    },
+                    (),
+                );
+            }))
+        });
+    }
+
+    stack.update().unwrap();
+    stack.update().unwrap();
+
+    let mut res = rx.try_iter().collect::<Vec<_>>();
+    res.sort();
+
+    assert_eq!(res, [11, 12, 21, 22, 31, 32]);
+    assert!(LayerCtx::task().is_none());
+}
+
+#[test]
+fn scheduler_main_thread_task_ok() {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).build();
+
+    stack.push_layer("0", |sp| {
+        let sender = sp.resolve::<SyncSender<i32>>()?;
+
+        Ok(FnLayer(move |scheduler: &mut LayerScheduler| {
+            let sender = sender.clone();
+
+            scheduler.schedule(
+                async move {
+                    std::thread::sleep(Duration::from_millis(20));
+                    sender.send(0).unwrap();
+                },
+                (),
+            );
+        }))
+    });
+
+    stack.push_layer("1", |sp| {
+        let sender = sp.resolve::<SyncSender<i32>>()?;
+
+        Ok(FnLayer(move |scheduler: &mut LayerScheduler| {
+            let sender = sender.clone();
+            let thread = std::thread::current().id();
+
+            // Not `Send` state allowed in main thread task
+            let data = std::rc::Rc::new(1);
+
+            scheduler.schedule_main_thread(
+                async move {
+                    assert_eq!(std::thread::current().id(), thread);
+                    assert_eq!(LayerCtx::task().unwrap().name, "1");
+
+                    sender.send(*data).unwrap();
+                },
+                ["0"],
+                TaskOptions::new(),
+            );
+        }))
+    });
+
+    stack.push_layer("2", |sp| Ok(DepLayer::new(2, ["1"], sp)?));
+
+    stack.update().unwrap();
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [0, 1, 2]);
+    assert!(stack.scheduler().diagnostics().is_empty());
+}
+
+#[test]
+fn scheduler_main_thread_task_result_ok() {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).build();
+
+    stack.push_layer("0", |sp| {
+        let sender = sp.resolve::<SyncSender<i32>>()?;
+
+        Ok(FnLayer(move |scheduler: &mut LayerScheduler| {
+            let sender = sender.clone();
+
+            let handle = scheduler.schedule_main_thread(async { 10 }, (), TaskOptions::new());
+
+            // Worker task waits main thread task result
+            scheduler.schedule(
+                async move {
+                    sender.send(handle.join().await.unwrap() + 1).unwrap();
+                },
+                (),
+            );
+
+            scheduler.schedule_main_thread(async { panic!("Main thread task panic") }, (), TaskOptions::new());
+        }))
+    });
+
+    stack.update().unwrap();
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [11]);
+
+    let errors = stack.get_layer("0").unwrap().errors();
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].stage, LayerErrorStage::Task);
+}
+
+#[test]
+fn scheduler_parallel_ok() {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).build();
+
+    stack.push_layer("0", |sp| {
+        let sender = sp.resolve::<SyncSender<i32>>()?;
+
+        Ok(FnLayer(move |scheduler: &mut LayerScheduler| {
+            let sender = sender.clone();
+
+            scheduler.schedule_parallel(
+                (100..110).collect(),
+                4,
+                move |item| {
+                    std::thread::sleep(Duration::from_millis(5));
+                    sender.send(item).unwrap();
+                },
+                (),
+            );
+        }))
+    });
+
+    stack.push_layer("1", |sp| Ok(DepLayer::new(1, ["0"], sp)?));
+
+    stack.update().unwrap();
+
+    let mut res = rx.try_iter().collect::<Vec<_>>();
+
+    // Dependent layer waits all chunks
+    assert_eq!(res.pop(), Some(1));
+
+    res.sort();
+
+    assert_eq!(res, (100..110).collect::<Vec<_>>());
+    assert!(stack.scheduler().diagnostics().is_empty());
+}
+
+#[test]
+fn scheduler_parallel_with_result_ok() {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).build();
+
+    stack.push_layer("0", |sp| {
+        let sender = sp.resolve::<SyncSender<i32>>()?;
+
+        Ok(FnLayer(move |scheduler: &mut LayerScheduler| {
+            let sender = sender.clone();
+
+            let handle = scheduler.schedule_parallel_with_result((0..10).collect(), 3, |item: i32| item * 2, ());
+
+            scheduler.schedule(
+                async move {
+                    for item in handle.join().await.unwrap() {
+                        sender.send(item).unwrap();
+                    }
+                },
+                (),
+            );
+
+            scheduler.schedule_parallel(
+                (0..10).collect(),
+                3,
+                |item: i32| assert_ne!(item, 7, "Chunk panic"),
+                (),
+            );
+        }))
+    });
+
+    stack.update().unwrap();
+
+    // Results in items order
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), (0..10).map(|item| item * 2).collect::<Vec<_>>());
+
+    let errors = stack.get_layer("0").unwrap().errors();
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].stage, LayerErrorStage::Task);
+}
@@ -0,0 +1,94 @@
+use std::sync::mpsc;
+
+use crate::{
+    layer::LayerPosition,
+    stage::Stage,
+    types::error::LayersStackError,
+};
+
+use super::{layer::Layer, testing::sender_stack};
+
+#[test]
+fn stage_order_ok() {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).build();
+
+    // Render layers registered before gameplay layers
+    stack.push_layer("render", |sp| Ok(Layer::new(50, sp)?)).with_stage(Stage::Render);
+    stack.push_layer("pre_render", |sp| Ok(Layer::new(40, sp)?)).with_stage(Stage::PreRender);
+    stack.push_layer("movement", |sp| Ok(Layer::new(21, sp)?)).after("input");
+    stack.push_layer("physics", |sp| Ok(Layer::new(22, sp)?));
+    stack.push_layer("input", |sp| Ok(Layer::new(20, sp)?)).before("physics");
+    stack.push_layer("first", |sp| Ok(Layer::new(0, sp)?)).with_stage(Stage::First);
+    stack.push_layer("last", |sp| Ok(Layer::new(60, sp)?)).with_stage(Stage::Last);
+
+    stack.update().unwrap();
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [0, 20, 21, 22, 40, 50, 60]);
+
+    // Inserted layer keeps position inside own stage
+    stack
+        .insert_layer(LayerPosition::First, "late_input", |sp| Ok(Layer::new(23, sp)?))
+        .unwrap()
+        .after("physics");
+
+    stack.update().unwrap();
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [0, 20, 21, 22, 23, 40, 50, 60]);
+    assert_eq!(stack.layers().next().unwrap().stage(), Stage::First);
+
+    // Stage changed after attach resorts layers
+    stack.get_layer_mut("first").unwrap().with_stage(Stage::Last);
+
+    stack.update().unwrap();
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [20, 21, 22, 23, 40, 50, 0, 60]);
+
+    // Move next to other stage layer would be undone by sort
+    assert!(matches!(
+        stack.move_layer("render", LayerPosition::Before("input".into())).unwrap_err(),
+        LayersStackError::CrossStageMove { layer, target } if layer == "render" && target == "input"
+    ));
+
+    stack.move_layer("physics", LayerPosition::Before("movement".into())).unwrap();
+
+    stack.update().unwrap();
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [20, 22, 21, 23, 40, 50, 0, 60]);
+}
+
+#[test]
+fn stage_cross_stage_constraint_ignored_ok() {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).build();
+
+    stack.push_layer("render", |sp| Ok(Layer::new(1, sp)?)).with_stage(Stage::Render).before("update");
+    stack.push_layer("update", |sp| Ok(Layer::new(0, sp)?)).before("unknown");
+
+    stack.update().unwrap();
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [0, 1]);
+}
+
+#[test]
+fn stage_order_cycle_err() {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).build();
+
+    stack.push_layer("0", |sp| Ok(Layer::new(0, sp)?)).after("1");
+    stack.push_layer("1", |sp| Ok(Layer::new(1, sp)?)).after("0");
+    stack.push_layer("2", |sp| Ok(Layer::new(2, sp)?));
+
+    let Err(LayersStackError::OrderCycle { layers }) = stack.sort_layers() else {
+        panic!("Expected order cycle error");
+    };
+
+    assert_eq!(layers, ["0", "1"]);
+
+    // Layers not updated while order invalid
+    assert!(stack.update().is_err());
+    assert_eq!(rx.try_iter().count(), 0);
+}
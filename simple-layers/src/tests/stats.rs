@@ -0,0 +1,100 @@
+use std::sync::mpsc::{self, Sender as SyncSender};
+
+use chrono::TimeDelta;
+use xdi::{ServiceProvider, types::error::ServiceBuildResult};
+
+use crate::{
+    layer::{ILayer, LayersStack},
+    scheduler::LayerScheduler,
+    stats::FrameStats,
+};
+
+use super::testing::sender_stack;
+
+/// Send frame index read from stats
+#[derive(Debug)]
+pub struct StatsLayer {
+    stats: FrameStats,
+    sender: SyncSender<i32>,
+}
+
+impl StatsLayer {
+    pub fn new(sp: ServiceProvider) -> ServiceBuildResult<Self> {
+        Ok(Self {
+            stats: sp.resolve()?,
+            sender: sp.resolve()?,
+        })
+    }
+}
+
+impl ILayer for StatsLayer {
+    fn on_update(&mut self, _dt: &TimeDelta, _scheduler: &mut LayerScheduler) -> anyhow::Result<()> {
+        self.sender.send(self.stats.frame() as i32).unwrap();
+
+        Ok(())
+    }
+}
+
+#[test]
+fn stats_summary_ok() {
+    let (tx, _rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).build();
+
+    stack.clock().set_manual();
+    stack.stats().set_smoothing(0.5);
+    stack.stats().set_window_len(3);
+
+    // Zero dt frame counted without timings
+    stack.update().unwrap();
+
+    assert_eq!(stack.stats().frame(), 1);
+    assert_eq!(stack.stats().fps(), 0.0);
+
+    for ms in [10, 20, 30, 40] {
+        stack.step(TimeDelta::milliseconds(ms)).unwrap();
+    }
+
+    let summary = stack.stats().summary();
+
+    assert_eq!(summary.frame, 5);
+    assert_eq!(summary.dt, TimeDelta::milliseconds(40));
+    assert_eq!(summary.smoothed_dt, TimeDelta::microseconds(31_250));
+    assert_eq!(summary.fps, 32.0);
+    assert_eq!(summary.min_dt, TimeDelta::milliseconds(20));
+    assert_eq!(summary.max_dt, TimeDelta::milliseconds(40));
+    assert_eq!(summary.p50, TimeDelta::milliseconds(30));
+    assert_eq!(summary.p95, TimeDelta::milliseconds(40));
+    assert_eq!(summary.p99, TimeDelta::milliseconds(40));
+
+    stack.stats().reset();
+
+    assert_eq!(stack.stats().summary().max_dt, TimeDelta::zero());
+    assert_eq!(stack.stats().frame(), 5);
+}
+
+#[test]
+fn stats_nested_stack_frame_ok() {
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).build();
+
+    stack.clock().set_manual();
+
+    stack.push_layer("before", |sp| Ok(StatsLayer::new(sp)?));
+    stack.push_layer("nested", |sp| {
+        let mut stack = sp.resolve::<LayersStack>()?;
+
+        stack.push_layer("inner", |sp| Ok(StatsLayer::new(sp)?));
+
+        Ok(stack)
+    });
+
+    for _ in 0..2 {
+        stack.step(TimeDelta::milliseconds(10)).unwrap();
+    }
+
+    // Nested stack update is part of root frame
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [1, 1, 2, 2]);
+    assert_eq!(stack.stats().frame(), 2);
+}
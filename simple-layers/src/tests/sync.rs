@@ -0,0 +1,268 @@
+use std::{
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+use tokio::runtime::{Builder, Runtime};
+
+use crate::{
+    scheduler::LayerScheduler,
+    types::sync::SignalPool,
+};
+
+use super::{scheduler::FnLayer, testing::sender_stack};
+
+const BENCH_TASKS: usize = 500;
+const BENCH_DEPENDENTS: usize = 4;
+const BENCH_FRAMES: usize = 50;
+
+#[test]
+fn sync_completion_many_waiters_ok() {
+    let runtime = Builder::new_multi_thread()
+        .worker_threads(2)
+        .build()
+        .unwrap();
+
+    let mut pool = SignalPool::default();
+
+    let (waker, waiter) = pool.channel("0");
+
+    let (tx, rx) = mpsc::channel::<i32>();
+
+    let tasks = (0..8)
+        .map(|idx| {
+            let waiter = waiter.clone();
+            let tx = tx.clone();
+
+            runtime.spawn(async move {
+                waiter.wait().await;
+                tx.send(idx).unwrap();
+            })
+        })
+        .collect::<Vec<_>>();
+
+    std::thread::sleep(Duration::from_millis(20));
+
+    assert!(rx.try_recv().is_err());
+    assert!(!waiter.is_completed());
+
+    waker.signal();
+
+    runtime.block_on(async {
+        for task in tasks {
+            task.await.unwrap();
+        }
+    });
+
+    let mut res = rx.try_iter().collect::<Vec<_>>();
+    res.sort();
+
+    assert_eq!(res, (0..8).collect::<Vec<_>>());
+
+    // Waiter cloned after signal completes immediately
+    runtime.block_on(waiter.clone().wait());
+
+    let (waker, waiter) = pool.channel("1");
+
+    // Dropped task does not block dependents
+    drop(waker);
+
+    assert!(waiter.is_completed());
+}
+
+#[test]
+fn sync_pool_recycle_ok() {
+    let mut pool = SignalPool::default();
+
+    let (waker, held) = pool.channel("0");
+    waker.signal();
+
+    let (waker, dropped) = pool.channel("1");
+    waker.signal();
+    drop(dropped);
+
+    pool.recycle();
+
+    // Recycled event reset, held event not reused
+    let (_waker, waiter) = pool.channel("2");
+    let (_waker, other) = pool.channel("3");
+
+    assert!(held.is_completed());
+    assert!(!waiter.is_completed());
+    assert!(!other.is_completed());
+}
+
+/// Per task overhead of broadcast channel baseline against pooled completion events and scheduler frame,
+/// run by `cargo test --release -p simple-layers sync_overhead_bench -- --ignored --nocapture`
+#[test]
+#[ignore]
+fn sync_overhead_bench() {
+    let current_thread = Builder::new_current_thread().enable_all().build().unwrap();
+    let workers = Builder::new_multi_thread().worker_threads(2).enable_all().build().unwrap();
+
+    report_per_task("signal, broadcast channel", current_thread.block_on(bench_broadcast_signals()));
+    report_per_task("signal, pooled completion event", current_thread.block_on(bench_pooled_signals()));
+
+    report_per_task("task graph, broadcast channel", bench_broadcast_graph(&workers));
+    report_per_task("task graph, pooled completion event", bench_pooled_graph(&workers));
+
+    report_per_task("scheduler frame", bench_scheduler());
+}
+
+fn report_per_task(name: &str, elapsed: Duration) {
+    let per_task = elapsed.as_nanos() / (BENCH_FRAMES * BENCH_TASKS) as u128;
+
+    println!("{name}: {per_task} ns per task");
+}
+
+/// Signal and wait every task by `BENCH_DEPENDENTS` waiters on single thread
+async fn bench_broadcast_signals() -> Duration {
+    let start = Instant::now();
+
+    for _ in 0..BENCH_FRAMES {
+        let mut receivers = Vec::with_capacity(BENCH_TASKS * BENCH_DEPENDENTS);
+        let mut senders = Vec::with_capacity(BENCH_TASKS);
+
+        for _ in 0..BENCH_TASKS {
+            let (sender, receiver) = async_broadcast::broadcast::<()>(1);
+
+            receivers.extend((0..BENCH_DEPENDENTS).map(|_| receiver.clone()));
+            senders.push(sender);
+        }
+
+        for sender in senders {
+            sender.broadcast(()).await.unwrap();
+        }
+
+        for mut receiver in receivers {
+            receiver.recv().await.unwrap();
+        }
+    }
+
+    start.elapsed()
+}
+
+async fn bench_pooled_signals() -> Duration {
+    let mut pool = SignalPool::default();
+
+    let start = Instant::now();
+
+    for _ in 0..BENCH_FRAMES {
+        pool.recycle();
+
+        let mut waiters = Vec::with_capacity(BENCH_TASKS * BENCH_DEPENDENTS);
+        let mut wakers = Vec::with_capacity(BENCH_TASKS);
+
+        for _ in 0..BENCH_TASKS {
+            let (waker, waiter) = pool.channel("layer");
+
+            waiters.extend((0..BENCH_DEPENDENTS).map(|_| waiter.clone()));
+            wakers.push(waker);
+        }
+
+        for waker in wakers {
+            waker.signal();
+        }
+
+        for waiter in waiters {
+            waiter.wait().await;
+        }
+    }
+
+    start.elapsed()
+}
+
+/// Tasks spawned on workers, every task waits `BENCH_DEPENDENTS` previous tasks like scheduler dependencies
+fn bench_broadcast_graph(runtime: &Runtime) -> Duration {
+    let start = Instant::now();
+
+    for _ in 0..BENCH_FRAMES {
+        let mut receivers = Vec::with_capacity(BENCH_TASKS);
+        let mut tasks = Vec::with_capacity(BENCH_TASKS);
+
+        for idx in 0..BENCH_TASKS {
+            let (sender, receiver) = async_broadcast::broadcast::<()>(1);
+
+            let deps = receivers[idx.saturating_sub(BENCH_DEPENDENTS)..].to_vec();
+            receivers.push(receiver);
+
+            tasks.push(runtime.spawn(async move {
+                for mut dep in deps {
+                    dep.recv().await.unwrap();
+                }
+
+                sender.broadcast(()).await.unwrap();
+            }));
+        }
+
+        runtime.block_on(async {
+            for task in tasks {
+                task.await.unwrap();
+            }
+        });
+    }
+
+    start.elapsed()
+}
+
+fn bench_pooled_graph(runtime: &Runtime) -> Duration {
+    let mut pool = SignalPool::default();
+
+    let start = Instant::now();
+
+    for _ in 0..BENCH_FRAMES {
+        pool.recycle();
+
+        let mut waiters = Vec::with_capacity(BENCH_TASKS);
+        let mut tasks = Vec::with_capacity(BENCH_TASKS);
+
+        for idx in 0..BENCH_TASKS {
+            let (waker, waiter) = pool.channel("layer");
+
+            let deps = waiters[idx.saturating_sub(BENCH_DEPENDENTS)..].to_vec();
+            waiters.push(waiter);
+
+            tasks.push(runtime.spawn(async move {
+                for dep in deps {
+                    dep.wait().await;
+                }
+
+                waker.signal();
+            }));
+        }
+
+        runtime.block_on(async {
+            for task in tasks {
+                task.await.unwrap();
+            }
+        });
+    }
+
+    start.elapsed()
+}
+
+/// Scheduler frame time, including frame graph resolve and wait
+fn bench_scheduler() -> Duration {
+    let (tx, _rx) = mpsc::channel::<i32>();
+
+    let mut stack = sender_stack(tx).with_profiler(false).build();
+
+    stack.push_layer("0", |_| {
+        Ok(FnLayer(|scheduler: &mut LayerScheduler| {
+            for _ in 0..BENCH_TASKS {
+                scheduler.schedule(async {}, ());
+            }
+        }))
+    });
+
+    // Warm up pool
+    stack.update().unwrap();
+
+    let start = Instant::now();
+
+    for _ in 0..BENCH_FRAMES {
+        stack.update().unwrap();
+    }
+
+    start.elapsed()
+}
@@ -0,0 +1,178 @@
+use std::{
+    sync::{
+        Arc, Barrier,
+        mpsc::{self, Sender as SyncSender},
+    },
+    time::Duration,
+};
+
+use chrono::{DateTime, TimeDelta};
+use xdi::{ServiceProvider, types::error::ServiceBuildResult};
+
+use crate::{
+    layer::{ILayer, ILayersSource, LayersStack},
+    scheduler::LayerScheduler,
+    testing::{Recorder, TestStack, TestStackBuilder},
+};
+
+use super::{layer::LifecycleLayer, scheduler::FnLayer};
+
+/// Test stack builder with `SyncSender` resolved by test layers
+pub fn sender_stack<T: Send + 'static>(tx: SyncSender<T>) -> TestStackBuilder {
+    TestStack::builder().register(move |builder| {
+        builder.thread_local(move |_| Ok(tx.clone()));
+    })
+}
+
+/// Record update and task run, task sleeps to make overlapping tasks observable
+#[derive(Debug)]
+pub struct RecordLayer<const N: usize> {
+    deps: [&'static str; N],
+    recorder: Recorder,
+}
+
+impl<const N: usize> RecordLayer<N> {
+    pub fn new(deps: [&'static str; N], sp: ServiceProvider) -> ServiceBuildResult<Self> {
+        Ok(Self {
+            deps,
+            recorder: sp.resolve()?,
+        })
+    }
+}
+
+impl<const N: usize> ILayer for RecordLayer<N> {
+    fn on_update(&mut self, _dt: &chrono::TimeDelta, scheduler: &mut LayerScheduler) -> anyhow::Result<()> {
+        self.recorder.record("update");
+
+        let recorder = self.recorder.clone();
+
+        scheduler.schedule(
+            async move {
+                std::thread::sleep(Duration::from_millis(10));
+                recorder.record("task");
+            },
+            self.deps,
+        );
+
+        Ok(())
+    }
+}
+
+pub struct RecordLayersSource;
+
+impl ILayersSource for RecordLayersSource {
+    fn register(layers_stack: &mut LayersStack) {
+        layers_stack.push_layer("a", |sp| Ok(RecordLayer::new([], sp)?));
+        layers_stack.push_layer("b", |sp| Ok(RecordLayer::new(["a"], sp)?));
+        layers_stack.push_layer("c", |sp| Ok(RecordLayer::new([], sp)?));
+    }
+}
+
+#[test]
+fn testing_source_order_ok() {
+    let mut stack = TestStack::builder().with_source::<RecordLayersSource>().build();
+
+    stack.frame(TimeDelta::milliseconds(10));
+
+    stack.assert_layers_order(&["a", "b", "c"]);
+    stack.assert_tasks_before("a", "b");
+    stack.assert_tasks_count("b", 1);
+
+    let events = stack.recorder().take();
+
+    assert_eq!(events.len(), 6);
+    assert!(events.iter().all(|event| event.frame == 1));
+
+    let layer_events = |layer: &str| {
+        events
+            .iter()
+            .filter(|event| event.layer == layer)
+            .map(|event| event.label.as_str())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(layer_events("a"), ["update", "task"]);
+    assert_eq!(layer_events("b"), ["update", "task"]);
+
+    let id = stack.get_layer("c").unwrap().id();
+    stack.disable(id);
+    stack.frame(TimeDelta::milliseconds(10));
+
+    stack.assert_not_updated("c");
+    stack.assert_updated("b");
+}
+
+#[test]
+fn testing_independent_tasks_overlap_ok() {
+    let mut stack = TestStack::builder().with_worker_threads(2).build();
+
+    // Tasks wait each other, so complete only if run in parallel
+    let barrier = Arc::new(Barrier::new(2));
+
+    for layer in ["a", "c"] {
+        let barrier = barrier.clone();
+
+        stack.push_layer(layer, move |_| {
+            let barrier = barrier.clone();
+
+            Ok(FnLayer(move |scheduler: &mut LayerScheduler| {
+                let barrier = barrier.clone();
+
+                scheduler.schedule(
+                    async move {
+                        barrier.wait();
+                    },
+                    (),
+                );
+            }))
+        });
+    }
+
+    stack.frame(TimeDelta::milliseconds(10));
+
+    let frame = stack.last_frame();
+    let span = |layer: &str| frame.tasks.iter().find(|task| task.layer_name == layer).unwrap().span;
+
+    let (a, c) = (span("a"), span("c"));
+
+    assert!(a.start < c.end && c.start < a.end);
+}
+
+#[test]
+fn testing_manual_clock_ok() {
+    let start = DateTime::from_timestamp(1_000, 0).unwrap();
+
+    let mut stack = TestStack::builder()
+        .with_clock_start(start)
+        .register(|builder| {
+            builder.singletone(|_| Ok(7_i32));
+        })
+        .build();
+
+    stack.push_layer("a", |sp| Ok(RecordLayer::new([], sp)?));
+
+    stack.frames(3, TimeDelta::milliseconds(20));
+
+    assert!(stack.clock().is_manual());
+    assert_eq!(stack.clock().now(), start + TimeDelta::milliseconds(60));
+    assert_eq!(stack.stats().frame(), 3);
+    assert_eq!(stack.stats().dt(), TimeDelta::milliseconds(20));
+    assert_eq!(stack.sp().resolve::<i32>().unwrap(), 7);
+
+    stack.recorder().assert_order(&["update", "task", "update", "task"]);
+}
+
+#[test]
+fn testing_drop_shutdown_ok() {
+    let (tx, rx) = mpsc::channel::<&'static str>();
+
+    let mut stack = sender_stack(tx).build();
+
+    stack.push_layer("0", |sp| Ok(LifecycleLayer::new(sp)?));
+
+    stack.frame(TimeDelta::milliseconds(10));
+
+    drop(stack);
+
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), ["attach", "update", "shutdown", "detach"]);
+}
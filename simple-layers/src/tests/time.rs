@@ -0,0 +1,97 @@
+use chrono::{TimeDelta, Utc};
+
+use crate::{
+    time::{Clock, FixedTimeState, FixedTimestep},
+    types::id::LayerId,
+};
+
+#[test]
+fn fixed_timestep_advance_ok() {
+    let mut fixed_timestep = FixedTimestep::new(TimeDelta::milliseconds(10));
+
+    assert_eq!(fixed_timestep.advance(&TimeDelta::milliseconds(5)), 0);
+    assert_eq!(fixed_timestep.alpha(), 0.5);
+
+    assert_eq!(fixed_timestep.advance(&TimeDelta::milliseconds(20)), 2);
+    assert_eq!(fixed_timestep.alpha(), 0.5);
+
+    assert_eq!(fixed_timestep.advance(&TimeDelta::milliseconds(5)), 1);
+    assert_eq!(fixed_timestep.alpha(), 0.0);
+}
+
+#[test]
+fn fixed_timestep_spiral_of_death_clamp_ok() {
+    let mut fixed_timestep = FixedTimestep::new(TimeDelta::milliseconds(10)).with_max_steps(3);
+
+    assert_eq!(fixed_timestep.advance(&TimeDelta::milliseconds(1005)), 3);
+    assert_eq!(fixed_timestep.alpha(), 0.5);
+
+    assert_eq!(fixed_timestep.advance(&TimeDelta::milliseconds(10)), 1);
+}
+
+#[test]
+fn fixed_timestep_from_rate_ok() {
+    assert_eq!(FixedTimestep::from_rate(50).step(), TimeDelta::milliseconds(20));
+}
+
+#[test]
+#[should_panic(expected = "Fixed timestep rate should be positive")]
+fn fixed_timestep_zero_rate_err() {
+    FixedTimestep::from_rate(0);
+}
+
+#[test]
+fn fixed_time_state_by_id_ok() {
+    let state = FixedTimeState::default();
+
+    // Same named layers of nested stacks keep own factors
+    let (parent, child) = (LayerId::new(), LayerId::new());
+
+    state.set_alpha(parent, 0.25);
+    state.set_alpha(child, 0.75);
+
+    assert_eq!(state.alpha(parent), Some(0.25));
+    assert_eq!(state.alpha(child), Some(0.75));
+
+    state.remove(child);
+
+    assert_eq!(state.alpha(child), None);
+}
+
+#[test]
+fn clock_manual_ok() {
+    let start = Utc::now();
+    let clock = Clock::manual(start);
+
+    assert!(clock.is_manual());
+    assert_eq!(clock.now(), start);
+
+    assert!(clock.advance(TimeDelta::milliseconds(15)));
+    assert_eq!(clock.now() - start, TimeDelta::milliseconds(15));
+
+    clock.set_real();
+
+    assert!(!clock.advance(TimeDelta::milliseconds(15)));
+}
+
+#[test]
+fn clock_scaled_ok() {
+    let clock = Clock::scaled(0.0);
+
+    let paused = clock.now();
+    std::thread::sleep(std::time::Duration::from_millis(5));
+
+    assert_eq!(clock.now(), paused);
+
+    clock.set_scale(2.0);
+    std::thread::sleep(std::time::Duration::from_millis(5));
+
+    assert!(clock.now() - paused >= TimeDelta::milliseconds(10));
+
+    clock.set_manual();
+
+    let stopped = clock.now();
+
+    assert!(clock.advance(TimeDelta::seconds(1)));
+    assert_eq!(clock.now() - stopped, TimeDelta::seconds(1));
+}
@@ -0,0 +1,214 @@
+use std::{collections::HashMap, sync::Arc};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use parking_lot::{Mutex, RwLock};
+use xdi::{ServiceProvider, types::error::ServiceBuildResult};
+
+use crate::types::id::LayerId;
+
+const DEFAULT_MAX_STEPS: u32 = 5;
+
+/// Fixed rate update settings and accumulator for layer
+#[derive(Debug, Clone, Copy)]
+pub struct FixedTimestep {
+    step: TimeDelta,
+    max_steps: u32,
+    accumulator: TimeDelta,
+}
+
+impl FixedTimestep {
+    pub fn new(step: TimeDelta) -> Self {
+        assert!(step > TimeDelta::zero(), "Fixed timestep should be positive");
+
+        Self {
+            step,
+            max_steps: DEFAULT_MAX_STEPS,
+            accumulator: TimeDelta::zero(),
+        }
+    }
+
+    /// Step of `hz` updates per second, panics on zero rate
+    pub fn from_rate(hz: u32) -> Self {
+        assert!(hz > 0, "Fixed timestep rate should be positive");
+
+        Self::new(TimeDelta::nanoseconds(1_000_000_000 / hz as i64))
+    }
+
+    /// Max updates per frame, rest accumulated time dropped to avoid spiral of death
+    pub fn with_max_steps(mut self, max_steps: u32) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    pub fn step(&self) -> TimeDelta {
+        self.step
+    }
+
+    /// Leftover part of step in `[0, 1)`, for interpolation between last two fixed updates
+    pub fn alpha(&self) -> f64 {
+        self.accumulator.num_nanoseconds().unwrap_or(0) as f64 / self.step.num_nanoseconds().unwrap_or(1) as f64
+    }
+
+    /// Accumulate frame time and return fixed updates count for this frame
+    pub fn advance(&mut self, dt: &TimeDelta) -> u32 {
+        self.accumulator += *dt;
+
+        let mut steps = 0;
+
+        while self.accumulator >= self.step && steps < self.max_steps {
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+
+        if self.accumulator >= self.step {
+            let step = self.step.num_nanoseconds().unwrap_or(1);
+            let accumulator = self.accumulator.num_nanoseconds().unwrap_or(0);
+
+            let leftover = TimeDelta::nanoseconds(accumulator % step);
+
+            tracing::warn!("Fixed timestep can't keep up, {:?} dropped", self.accumulator - leftover);
+
+            self.accumulator = leftover;
+        }
+
+        steps
+    }
+}
+
+/// Interpolation factors of fixed rate layers by layer id, updated by `LayersStack::update`.
+/// Layer reads own factor by `LayerCtx::id`, ids unique across nested stacks
+#[derive(Debug, Clone, Default)]
+pub struct FixedTimeState {
+    inner: Arc<RwLock<HashMap<LayerId, f64, ahash::RandomState>>>,
+}
+
+impl FixedTimeState {
+    pub fn new(_: ServiceProvider) -> ServiceBuildResult<Self> {
+        Ok(Self::default())
+    }
+
+    pub fn alpha(&self, layer: LayerId) -> Option<f64> {
+        self.inner.read().get(&layer).copied()
+    }
+
+    pub(crate) fn set_alpha(&self, layer: LayerId, alpha: f64) {
+        self.inner.write().insert(layer, alpha);
+    }
+
+    pub(crate) fn remove(&self, layer: LayerId) {
+        self.inner.write().remove(&layer);
+    }
+}
+
+/// Frames time source of `LayersStack::update`, real by default.
+/// Switched at runtime or replaced by registering after `register_layers_system_dependencies`
+#[derive(Debug, Clone)]
+pub struct Clock {
+    source: Arc<Mutex<ClockSource>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ClockSource {
+    Real,
+    Manual {
+        now: DateTime<Utc>,
+    },
+    /// Real time since `real_origin` multiplied by scale and added to `origin`
+    Scaled {
+        scale: f64,
+        origin: DateTime<Utc>,
+        real_origin: DateTime<Utc>,
+    },
+}
+
+impl Clock {
+    pub fn new(_: ServiceProvider) -> ServiceBuildResult<Self> {
+        Ok(Self::real())
+    }
+
+    pub fn real() -> Self {
+        Self::from_source(ClockSource::Real)
+    }
+
+    /// Time changed only by `advance`
+    pub fn manual(start: DateTime<Utc>) -> Self {
+        Self::from_source(ClockSource::Manual { now: start })
+    }
+
+    /// Real time multiplied by scale, zero scale pauses time
+    pub fn scaled(scale: f64) -> Self {
+        let now = Utc::now();
+
+        Self::from_source(ClockSource::Scaled {
+            scale,
+            origin: now,
+            real_origin: now,
+        })
+    }
+
+    fn from_source(source: ClockSource) -> Self {
+        Self {
+            source: Arc::new(Mutex::new(source)),
+        }
+    }
+
+    pub fn now(&self) -> DateTime<Utc> {
+        Self::source_now(&self.source.lock())
+    }
+
+    fn source_now(source: &ClockSource) -> DateTime<Utc> {
+        match *source {
+            ClockSource::Real => Utc::now(),
+            ClockSource::Manual { now } => now,
+            ClockSource::Scaled {
+                scale,
+                origin,
+                real_origin,
+            } => {
+                let elapsed = (Utc::now() - real_origin).num_nanoseconds().unwrap_or(i64::MAX) as f64;
+
+                origin + TimeDelta::nanoseconds((elapsed * scale) as i64)
+            }
+        }
+    }
+
+    pub fn is_manual(&self) -> bool {
+        matches!(*self.source.lock(), ClockSource::Manual { .. })
+    }
+
+    /// Advance manual clock, return false for real and scaled clocks
+    pub fn advance(&self, dt: TimeDelta) -> bool {
+        match &mut *self.source.lock() {
+            ClockSource::Manual { now } => {
+                *now += dt;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Stop time at current clock time, next changes only by `advance`
+    pub fn set_manual(&self) {
+        let mut source = self.source.lock();
+
+        *source = ClockSource::Manual {
+            now: Self::source_now(&source),
+        };
+    }
+
+    /// Continue from current clock time with scaled real time
+    pub fn set_scale(&self, scale: f64) {
+        let mut source = self.source.lock();
+
+        *source = ClockSource::Scaled {
+            scale,
+            origin: Self::source_now(&source),
+            real_origin: Utc::now(),
+        };
+    }
+
+    /// Return to real time, clock time jumps if it diverged from real time
+    pub fn set_real(&self) {
+        *self.source.lock() = ClockSource::Real;
+    }
+}
@@ -0,0 +1,286 @@
+use std::{fmt::Display, sync::Arc};
+
+use chrono::{DateTime, Utc};
+
+use super::id::LayerId;
+
+/// Layer lifecycle stage where error occurred
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayerErrorStage {
+    Build,
+    Update,
+    /// Scheduled task panicked or timed out
+    Task,
+}
+
+#[derive(Debug, Clone)]
+pub struct LayerError {
+    pub id: LayerId,
+    pub name: String,
+    pub stage: LayerErrorStage,
+    pub time: DateTime<Utc>,
+    pub error: Arc<anyhow::Error>,
+}
+
+impl LayerError {
+    /// Error at `time` of stack clock
+    pub(crate) fn new(
+        id: LayerId,
+        name: String,
+        stage: LayerErrorStage,
+        time: DateTime<Utc>,
+        error: anyhow::Error,
+    ) -> Self {
+        Self {
+            id,
+            name,
+            stage,
+            time,
+            error: Arc::new(error),
+        }
+    }
+}
+
+impl Display for LayerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] <{}> Layer {:?} failed: {:?}", self.name, self.id, self.stage, self.error)
+    }
+}
+
+impl std::error::Error for LayerError {}
+
+#[derive(Debug, Clone)]
+pub enum LayersStackError {
+    LayerNotFound { layer: String },
+    DuplicateLayerName { name: String },
+    GroupNotFound { group: String },
+    /// Layers `before`/`after` constraints inside stage form cycle
+    OrderCycle { layers: Vec<String> },
+    /// Layer moved relative to layer of other stage
+    CrossStageMove { layer: String, target: String },
+    /// Layer failed with `LayerErrorPolicy::Abort` policy
+    Layer(LayerError),
+    Schedule(ScheduleError),
+}
+
+impl Display for LayersStackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::LayerNotFound { layer } => write!(f, "Layer [{layer}] not found"),
+            Self::DuplicateLayerName { name } => write!(f, "Layer [{name}] already registered"),
+            Self::GroupNotFound { group } => write!(f, "Layers group [{group}] not found"),
+            Self::OrderCycle { layers } => write!(f, "Layers order constraints cycle: {}", layers.join(", ")),
+            Self::CrossStageMove { layer, target } => {
+                write!(f, "Layer [{layer}] can't be moved next to layer [{target}] of other stage")
+            }
+            Self::Layer(err) => err.fmt(f),
+            Self::Schedule(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for LayersStackError {}
+
+impl From<LayerError> for LayersStackError {
+    fn from(value: LayerError) -> Self {
+        Self::Layer(value)
+    }
+}
+
+impl From<ScheduleError> for LayersStackError {
+    fn from(value: ScheduleError) -> Self {
+        Self::Schedule(value)
+    }
+}
+
+/// Scheduled task dependency problem, found while frame tasks graph built
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScheduleDiagnostic {
+    UnknownDependency { layer: String, dependency: String },
+    DisabledDependency { layer: String, dependency: String },
+    /// Layers tasks in dependency order, dependency closing the cycle ignored
+    Cycle { layers: Vec<String> },
+    /// Tasks conflicting access not ordered by dependencies, tasks ordered by schedule order
+    AccessConflict { resource: String, first: String, second: String },
+}
+
+impl Display for ScheduleDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownDependency { layer, dependency } => {
+                write!(f, "[{layer}] Task depends on unknown layer [{dependency}]")
+            }
+            Self::DisabledDependency { layer, dependency } => {
+                write!(f, "[{layer}] Task depends on disabled layer [{dependency}]")
+            }
+            Self::Cycle { layers } => write!(f, "Tasks dependency cycle: {}", layers.join(" -> ")),
+            Self::AccessConflict { resource, first, second } => {
+                write!(f, "[{second}] Task access to [{resource}] conflicts with [{first}] task and not ordered by dependencies")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduleError {
+    pub diagnostics: Vec<ScheduleDiagnostic>,
+}
+
+impl Display for ScheduleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Tasks schedule failed:")?;
+
+        for diagnostic in &self.diagnostics {
+            write!(f, " {diagnostic};")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for ScheduleError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskErrorKind {
+    Panic,
+    TimedOut,
+    Cancelled,
+    /// Task dropped without result, for example on runtime shutdown
+    Lost,
+}
+
+/// Scheduled task failure
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskError {
+    pub layer_id: LayerId,
+    pub layer_name: String,
+    pub kind: TaskErrorKind,
+    pub message: String,
+}
+
+impl TaskError {
+    pub(crate) fn new(layer_id: LayerId, layer_name: String, kind: TaskErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            layer_id,
+            layer_name,
+            kind,
+            message: message.into(),
+        }
+    }
+
+    pub(crate) fn lost(layer_id: LayerId, layer_name: &str) -> Self {
+        Self::new(layer_id, layer_name.to_string(), TaskErrorKind::Lost, "Task dropped without result")
+    }
+}
+
+impl Display for TaskError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] <{}> Task {:?}: {}", self.layer_name, self.layer_id, self.kind, self.message)
+    }
+}
+
+impl std::error::Error for TaskError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayersConfigError {
+    Io { path: String, message: String },
+    UnsupportedFormat { path: String },
+    Parse { message: String },
+    UnknownProfile { profile: String },
+    UnknownProfileTarget { profile: String, target: String },
+    /// Layer constructor not registered in `LayersRegistry`
+    UnknownLayer { layer: String, ty: String },
+    DuplicateLayerName { name: String },
+    InvalidParams { layer: String, message: String },
+    /// Layer `before`/`after` target neither in config nor in stack
+    UnknownOrderTarget { layer: String, target: String },
+}
+
+impl Display for LayersConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io { path, message } => write!(f, "Failed to read layers config [{path}]: {message}"),
+            Self::UnsupportedFormat { path } => write!(f, "Unsupported layers config format [{path}], expected json or toml"),
+            Self::Parse { message } => write!(f, "Failed to parse layers config: {message}"),
+            Self::UnknownProfile { profile } => write!(f, "Layers profile [{profile}] not found"),
+            Self::UnknownProfileTarget { profile, target } => {
+                write!(f, "Layers profile [{profile}] references unknown layer or group [{target}]")
+            }
+            Self::UnknownLayer { layer, ty } => write!(f, "Layer [{layer}] constructor [{ty}] not registered"),
+            Self::DuplicateLayerName { name } => write!(f, "Layer [{name}] declared more than once"),
+            Self::InvalidParams { layer, message } => write!(f, "Layer [{layer}] invalid params: {message}"),
+            Self::UnknownOrderTarget { layer, target } => {
+                write!(f, "Layer [{layer}] ordered relative to unknown layer [{target}]")
+            }
+        }
+    }
+}
+
+impl std::error::Error for LayersConfigError {}
+
+#[derive(Debug, Clone)]
+pub enum PluginError {
+    Load { path: String, message: String },
+    /// Plugin library does not export `export_plugin` symbols
+    MissingEntry { path: String },
+    AbiMismatch { path: String, expected: u32, found: u32 },
+    NotFound { plugin: String },
+    /// Services can be registered only by first loaded library
+    ServicesOnReload { plugin: String },
+    /// Failed plugins of `PluginLoader::poll_reload`
+    Reload { errors: Vec<PluginError> },
+    Stack(LayersStackError),
+}
+
+impl Display for PluginError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Load { path, message } => write!(f, "Failed to load plugin [{path}]: {message}"),
+            Self::MissingEntry { path } => write!(f, "Plugin [{path}] entry point not found"),
+            Self::AbiMismatch { path, expected, found } => {
+                write!(f, "Plugin [{path}] abi version {found}, expected {expected}")
+            }
+            Self::NotFound { plugin } => write!(f, "Plugin [{plugin}] not loaded"),
+            Self::ServicesOnReload { plugin } => write!(f, "Plugin [{plugin}] registered services on reload"),
+            Self::Reload { errors } => {
+                write!(f, "{} plugins reload failed", errors.len())?;
+
+                for err in errors {
+                    write!(f, "; {err}")?;
+                }
+
+                Ok(())
+            }
+            Self::Stack(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for PluginError {}
+
+impl From<LayersStackError> for PluginError {
+    fn from(value: LayersStackError) -> Self {
+        Self::Stack(value)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum InspectorError {
+    /// Endpoint is neither socket address nor `unix:<path>`
+    InvalidEndpoint { endpoint: String },
+    /// TCP endpoint not on loopback address, inspector requests are not authenticated
+    NotLoopback { endpoint: String },
+    Bind { endpoint: String, message: String },
+}
+
+impl Display for InspectorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidEndpoint { endpoint } => write!(f, "Invalid inspector endpoint [{endpoint}]"),
+            Self::NotLoopback { endpoint } => write!(f, "Inspector endpoint [{endpoint}] is not loopback address"),
+            Self::Bind { endpoint, message } => write!(f, "Failed to bind inspector [{endpoint}]: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for InspectorError {}
@@ -12,6 +12,8 @@ impl Display for LayerId {
 }
 
 impl LayerId {
+    /// Random id, no `Default` so every id is created explicitly
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         Self(Uuid::new_v4())
     }
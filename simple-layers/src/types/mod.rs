@@ -1,3 +1,5 @@
+pub mod error;
 pub mod id;
 pub mod sync;
+pub mod task;
 pub mod type_info;
@@ -1,44 +1,247 @@
-use async_broadcast::{Receiver, Sender};
+use std::{
+    future::poll_fn,
+    ptr,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicPtr, Ordering},
+    },
+    task::{Poll, Waker as TaskWaker},
+};
 
-#[derive(Debug, Clone)]
-pub struct Waiter{
-    name: String,
-    r: Receiver<()>
+use tokio::sync::Notify;
+
+/// Completion flag with any number of waiters, reused by `SignalPool` when not referenced anymore.
+/// Waiters pushed to lock-free stack, completion swaps stack with `COMPLETED` mark and wakes popped waiters
+#[derive(Debug, Default)]
+struct CompletionEvent {
+    waiters: AtomicPtr<WaiterNode>,
 }
 
-impl Waiter {
-    pub(crate) fn new(name: String, r: Receiver<()>) -> Self {
-        Self {
-            name,
-            r
+#[derive(Debug)]
+struct WaiterNode {
+    waker: TaskWaker,
+    next: *mut WaiterNode,
+}
+
+/// Stack head of completed event, never dereferenced
+const COMPLETED: *mut WaiterNode = ptr::dangling_mut();
+
+impl CompletionEvent {
+    fn is_completed(&self) -> bool {
+        self.waiters.load(Ordering::Acquire) == COMPLETED
+    }
+
+    fn complete(&self) {
+        let head = self.waiters.swap(COMPLETED, Ordering::AcqRel);
+
+        // SAFETY: popped nodes owned by this call only, stack replaced by mark atomically
+        for waker in unsafe { Self::take_wakers(head) } {
+            waker.wake();
         }
     }
 
-    pub async fn wait(mut self) {
-        self.r.recv().await.expect(&format!("Wait failed: [{}]", self.name));
+    /// `registered` is waker already pushed by same wait, repeated polls by same task not pushed again
+    fn poll_wait(&self, waker: &TaskWaker, registered: &mut Option<TaskWaker>) -> Poll<()> {
+        let mut head = self.waiters.load(Ordering::Acquire);
+
+        if head == COMPLETED {
+            return Poll::Ready(());
+        }
+
+        if registered.as_ref().is_some_and(|registered| registered.will_wake(waker)) {
+            return Poll::Pending;
+        }
+
+        let node = Box::into_raw(Box::new(WaiterNode {
+            waker: waker.clone(),
+            next: head,
+        }));
+
+        loop {
+            match self
+                .waiters
+                .compare_exchange_weak(head, node, Ordering::AcqRel, Ordering::Acquire)
+            {
+                Ok(_) => break,
+                // Completed while pushing, node never published
+                Err(COMPLETED) => {
+                    // SAFETY: node allocated above and not shared
+                    drop(unsafe { Box::from_raw(node) });
+                    return Poll::Ready(());
+                }
+                Err(current) => {
+                    head = current;
+                    // SAFETY: node not published yet
+                    unsafe { (*node).next = head };
+                }
+            }
+        }
+
+        *registered = Some(waker.clone());
+
+        Poll::Pending
+    }
+
+    fn reset(&self) {
+        let head = self.waiters.swap(ptr::null_mut(), Ordering::AcqRel);
+
+        // SAFETY: stack replaced by empty head atomically
+        drop(unsafe { Self::take_wakers(head) });
+    }
+
+    /// Free detached stack nodes, wakers returned in reverse push order
+    ///
+    /// # Safety
+    /// `head` should be detached from event, nodes owned by caller
+    unsafe fn take_wakers(mut head: *mut WaiterNode) -> Vec<TaskWaker> {
+        let mut wakers = Vec::new();
+
+        while !head.is_null() && head != COMPLETED {
+            // SAFETY: nodes allocated by `poll_wait` and detached by caller
+            let node = unsafe { Box::from_raw(head) };
+            head = node.next;
+            wakers.push(node.waker);
+        }
+
+        wakers
     }
 }
 
+impl Drop for CompletionEvent {
+    fn drop(&mut self) {
+        self.reset();
+    }
+}
+
+/// Task completion waiter, cheap to clone
 #[derive(Debug, Clone)]
+pub struct Waiter {
+    name: Arc<str>,
+    event: Arc<CompletionEvent>,
+}
+
+impl Waiter {
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_completed(&self) -> bool {
+        self.event.is_completed()
+    }
+
+    pub async fn wait(self) {
+        self.wait_ref().await;
+    }
+
+    pub(crate) async fn wait_ref(&self) {
+        let mut registered = None;
+
+        poll_fn(|cx| self.event.poll_wait(cx.waker(), &mut registered)).await
+    }
+}
+
+/// Task completion signal, dropped not signalled waker completes waiters too
+#[derive(Debug)]
 pub struct Waker {
-    name: String,
-    s: Sender<()>
+    name: Arc<str>,
+    event: Arc<CompletionEvent>,
 }
 
 impl Waker {
-    pub(crate) fn new(name: String, s: Sender<()>) -> Self {
-        Self {
-            name,
-            s
+    pub fn signal(self) {
+        self.event.complete();
+    }
+}
+
+impl Drop for Waker {
+    fn drop(&mut self) {
+        if !self.event.is_completed() {
+            tracing::debug!("[{}] Waker dropped without signal", self.name);
+            self.event.complete();
         }
     }
+}
+
+/// Completion events of scheduled tasks, events recycled on frame start once all waiters and wakers dropped
+#[derive(Debug, Default)]
+pub(crate) struct SignalPool {
+    free: Vec<Arc<CompletionEvent>>,
+    used: Vec<Arc<CompletionEvent>>,
+}
+
+impl SignalPool {
+    pub(crate) fn channel(&mut self, name: &str) -> (Waker, Waiter) {
+        let event = self.free.pop().unwrap_or_default();
+        let name = Arc::<str>::from(name);
 
-    pub async fn signal(self) {
-        self.s.broadcast(()).await.expect(&format!("Signal failed: [{}]", self.name));
+        self.used.push(event.clone());
+
+        (
+            Waker {
+                name: name.clone(),
+                event: event.clone(),
+            },
+            Waiter { name, event },
+        )
+    }
+
+    pub(crate) fn recycle(&mut self) {
+        let Self { free, used } = self;
+
+        // Only pool references event, so nobody can wait or signal it anymore
+        used.retain(|event| {
+            if Arc::strong_count(event) > 1 {
+                return true;
+            }
+
+            event.reset();
+            free.push(event.clone());
+
+            false
+        });
     }
 }
 
-pub(crate) fn signal_channel(name: String) -> (Waker, Waiter) {
-    let (s, r) = async_broadcast::broadcast(1);
-    (Waker::new(name.clone(), s), Waiter::new(name, r))
+/// Cancellation signal for scheduled tasks
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<CancellationTokenInner>,
+}
+
+#[derive(Debug, Default)]
+struct CancellationTokenInner {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::Release);
+        self.inner.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Complete when token cancelled
+    pub async fn cancelled(&self) {
+        loop {
+            let notified = self.inner.notify.notified();
+            let mut notified = std::pin::pin!(notified);
+
+            // Register before check, to not miss cancel between check and await
+            notified.as_mut().enable();
+
+            if self.is_cancelled() {
+                return;
+            }
+
+            notified.await;
+        }
+    }
 }
@@ -0,0 +1,173 @@
+use std::{
+    any::Any,
+    panic::AssertUnwindSafe,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use tokio::sync::oneshot::{self, error::TryRecvError};
+
+use super::{
+    error::TaskError,
+    id::LayerId,
+    sync::{CancellationToken, Waiter},
+    type_info::TypeInfo,
+};
+
+/// Scheduled task settings.
+/// Timeout require tokio runtime with enabled time driver
+#[derive(Debug, Clone, Default)]
+pub struct TaskOptions {
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) cancellation: Option<CancellationToken>,
+    pub(crate) access: Vec<ResourceAccess>,
+}
+
+impl TaskOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel task if it runs longer than timeout, counted after dependencies completed
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Cancel task by token, in addition to owning layer token
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = Some(cancellation);
+        self
+    }
+
+    /// Declare shared resource read, task ordered after earlier scheduled writers of the resource
+    pub fn reads<T: ?Sized + 'static>(self) -> Self {
+        self.with_access(TypeInfo::from_type::<T>(), false)
+    }
+
+    /// Declare shared resource write, task ordered after earlier scheduled readers and writers of the resource
+    pub fn writes<T: ?Sized + 'static>(self) -> Self {
+        self.with_access(TypeInfo::from_type::<T>(), true)
+    }
+
+    fn with_access(mut self, resource: TypeInfo, write: bool) -> Self {
+        match self.access.iter_mut().find(|access| access.resource == resource) {
+            Some(access) => access.write |= write,
+            None => self.access.push(ResourceAccess { resource, write }),
+        }
+
+        self
+    }
+}
+
+/// Declared task access to shared resource
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResourceAccess {
+    pub resource: TypeInfo,
+    pub write: bool,
+}
+
+impl ResourceAccess {
+    /// Tasks with conflicting access can't run in parallel
+    pub fn conflicts(&self, other: &Self) -> bool {
+        self.resource == other.resource && (self.write || other.write)
+    }
+}
+
+/// Tasks not completed before frame deadline
+#[derive(Debug, Clone)]
+pub struct StragglerReport {
+    pub deadline: Duration,
+    pub tasks: Vec<StragglerTask>,
+}
+
+#[derive(Debug, Clone)]
+pub struct StragglerTask {
+    pub layer_id: LayerId,
+    pub layer_name: String,
+    /// False if task still waits dependencies
+    pub started: bool,
+    /// Time since task start, or since schedule if task not started
+    pub elapsed: Duration,
+}
+
+/// Scheduled task result handle
+#[derive(Debug)]
+pub struct TaskHandle<T> {
+    layer_id: LayerId,
+    waiter: Waiter,
+    result: oneshot::Receiver<Result<T, TaskError>>,
+}
+
+impl<T> TaskHandle<T> {
+    pub(crate) fn new(layer_id: LayerId, waiter: Waiter, result: oneshot::Receiver<Result<T, TaskError>>) -> Self {
+        Self {
+            layer_id,
+            waiter,
+            result,
+        }
+    }
+
+    /// Waiter for usage as dependency
+    pub fn waiter(&self) -> Waiter {
+        self.waiter.clone()
+    }
+
+    pub(crate) fn into_waiter(self) -> Waiter {
+        self.waiter
+    }
+
+    /// Wait task result
+    pub async fn join(self) -> Result<T, TaskError> {
+        let Self { layer_id, waiter, result } = self;
+
+        result
+            .await
+            .unwrap_or_else(|_| Err(TaskError::lost(layer_id, waiter.name())))
+    }
+
+    /// Take task result if task completed, for example on next frame
+    pub fn try_take(&mut self) -> Option<Result<T, TaskError>> {
+        match self.result.try_recv() {
+            Ok(res) => Some(res),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Closed) => Some(Err(TaskError::lost(self.layer_id, self.waiter.name()))),
+        }
+    }
+}
+
+/// Catch panic while inner future polled
+pub(crate) struct CatchUnwind<F>(Pin<Box<F>>);
+
+impl<F: Future> CatchUnwind<F> {
+    pub(crate) fn new(future: F) -> Self {
+        Self(Box::pin(future))
+    }
+}
+
+impl<F: Future> Future for CatchUnwind<F> {
+    type Output = Result<F::Output, Box<dyn Any + Send>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let future = self.0.as_mut();
+
+        match std::panic::catch_unwind(AssertUnwindSafe(|| future.poll(cx))) {
+            Ok(Poll::Pending) => Poll::Pending,
+            Ok(Poll::Ready(res)) => Poll::Ready(Ok(res)),
+            Err(panic) => Poll::Ready(Err(panic)),
+        }
+    }
+}
+
+pub(crate) fn panic_message(panic: &(dyn Any + Send)) -> String {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        return message.to_string();
+    }
+
+    if let Some(message) = panic.downcast_ref::<String>() {
+        return message.clone();
+    }
+
+    "Unknown panic".to_string()
+}